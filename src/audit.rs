@@ -0,0 +1,371 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use eframe::egui;
+use gitlab::api::{paged, Pagination, Query};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configuration_schema::TargetConfig,
+    gitlab_variable::{ProjectVariablesEndpoint, Variable, VariableType},
+    graph::resolve_project,
+    matching::glob_match,
+    ProjectKey,
+};
+
+/// The CI/CD variables of a project visible in an environment, or all environments.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct VariableScope {
+    pub project: ProjectKey,
+    pub environment: Option<String>,
+}
+
+impl Display for VariableScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.project)?;
+        if let Some(environment) = &self.environment {
+            write!(f, " ({environment})")?;
+        }
+        Ok(())
+    }
+}
+
+impl VariableScope {
+    /// The environment scope GitLab variables written for this scope have.
+    fn environment_scope(&self) -> &str {
+        self.environment.as_deref().unwrap_or("*")
+    }
+}
+
+/// How the tasks writing a variable expect it to be configured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExpectedVariable {
+    pub protected: bool,
+    pub masked: bool,
+    pub file: bool,
+}
+
+impl ExpectedVariable {
+    fn variable_type(&self) -> VariableType {
+        if self.file {
+            VariableType::File
+        } else {
+            VariableType::EnvVar
+        }
+    }
+}
+
+/// A difference between the written and the actual CI/CD variables.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct VariableFinding {
+    pub scope: VariableScope,
+    /// The variable, empty if the variables of the scope can't be read.
+    pub variable: String,
+    pub problem: VariableProblem,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VariableProblem {
+    /// The variables of the project can't be read, none of them is checked.
+    Unreadable {
+        reason: String,
+    },
+    /// A task writes the variable but it does not exist.
+    Missing,
+    NotProtected,
+    NotMasked,
+    WrongType {
+        expected: VariableType,
+        actual: VariableType,
+    },
+    /// The variable exists but no task writes it.
+    Unmanaged,
+}
+
+impl Display for VariableFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (variable, scope) = (&self.variable, &self.scope);
+        match &self.problem {
+            VariableProblem::Unreadable { reason } => {
+                write!(f, "Variables of {scope} can't be read: {reason}")
+            }
+            VariableProblem::Missing => write!(f, "{variable} is missing in {scope}"),
+            VariableProblem::NotProtected => write!(f, "{variable} in {scope} is not protected"),
+            VariableProblem::NotMasked => write!(f, "{variable} in {scope} is not masked"),
+            VariableProblem::WrongType { expected, actual } => write!(
+                f,
+                "{variable} in {scope} is of type {actual:?} instead of {expected:?}"
+            ),
+            VariableProblem::Unmanaged => {
+                write!(f, "{variable} in {scope} is not written by any task")
+            }
+        }
+    }
+}
+
+/// Compares the variables written to `scope` with the `actual` variables of its project.
+///
+/// A variable scoped to the environment takes precedence over one for all environments.
+/// Only variables of exactly this scope can be unmanaged, so that variables for all
+/// environments are not reported once per environment.
+pub fn audit_variables(
+    scope: &VariableScope,
+    expected: &BTreeMap<String, ExpectedVariable>,
+    actual: &[Variable],
+) -> Vec<VariableFinding> {
+    let environment_scope = scope.environment_scope();
+    let mut visible: BTreeMap<&str, &Variable> = BTreeMap::new();
+    for variable in actual
+        .iter()
+        .filter(|v| glob_match(&v.environment_scope, environment_scope))
+    {
+        let exact = variable.environment_scope == environment_scope;
+        visible
+            .entry(&variable.key)
+            .and_modify(|found| {
+                if exact {
+                    *found = variable;
+                }
+            })
+            .or_insert(variable);
+    }
+
+    let finding = |variable: &str, problem| VariableFinding {
+        scope: scope.clone(),
+        variable: variable.to_owned(),
+        problem,
+    };
+    let mut findings = Vec::new();
+    for (key, expected) in expected {
+        let Some(variable) = visible.get(key.as_str()) else {
+            findings.push(finding(key, VariableProblem::Missing));
+            continue;
+        };
+        if expected.protected && !variable.protected {
+            findings.push(finding(key, VariableProblem::NotProtected));
+        }
+        if expected.masked && !variable.masked {
+            findings.push(finding(key, VariableProblem::NotMasked));
+        }
+        if expected.variable_type() != variable.variable_type {
+            findings.push(finding(
+                key,
+                VariableProblem::WrongType {
+                    expected: expected.variable_type(),
+                    actual: variable.variable_type,
+                },
+            ));
+        }
+    }
+    for variable in actual
+        .iter()
+        .filter(|v| v.environment_scope == environment_scope && !expected.contains_key(&v.key))
+    {
+        findings.push(finding(&variable.key, VariableProblem::Unmanaged));
+    }
+    findings.sort();
+    findings
+}
+
+impl super::ConfigAnalyzer {
+    /// The CI/CD variables the analyzed tasks write, by project and environment.
+    pub(crate) fn expected_variables(
+        &self,
+    ) -> BTreeMap<VariableScope, BTreeMap<String, ExpectedVariable>> {
+        let mut expected: BTreeMap<VariableScope, BTreeMap<String, ExpectedVariable>> =
+            BTreeMap::new();
+        for (owner, files) in &self.project_configs {
+            for task in crate::loader::tasks(files) {
+                let TargetConfig::GitlabProjectVariables { config, details } = &task.target else {
+                    continue;
+                };
+                let scope = VariableScope {
+                    project: resolve_project(owner, config),
+                    environment: config.environment.clone(),
+                };
+                let listed = |list: Option<&Vec<String>>, key: &str| {
+                    list.is_some_and(|list| list.iter().any(|listed| listed == key))
+                };
+                let variables = expected.entry(scope).or_default();
+                for key in task.mapping.values().flatten().map(|target| target.key()) {
+                    let variable = variables.entry(key.to_owned()).or_default();
+                    if let Some(details) = details {
+                        variable.protected |= listed(details.protected_variables.as_ref(), key);
+                        variable.masked |= listed(details.masked_variables.as_ref(), key);
+                        variable.file |= listed(details.files.as_ref(), key);
+                    }
+                }
+            }
+        }
+        expected
+    }
+
+    /// Compares the written CI/CD variables with the variables of the connected projects.
+    ///
+    /// Projects which cannot be read are reported as [VariableProblem::Unreadable] once per
+    /// scope and requested only once.
+    pub(crate) fn audit_ci_variables(&self) -> Vec<VariableFinding> {
+        let mut actual: BTreeMap<ProjectKey, Result<Vec<Variable>, String>> = BTreeMap::new();
+        let mut findings = Vec::new();
+        for (scope, expected) in self.expected_variables() {
+            let project = &scope.project;
+            let variables = actual.entry(project.clone()).or_insert_with(|| {
+                let client = self
+                    .clients
+                    .get(&*project.host)
+                    .ok_or_else(|| format!("not connected to {}", project.host))?;
+                let endpoint = ProjectVariablesEndpoint {
+                    project: project.id,
+                };
+                paged(endpoint, Pagination::All)
+                    .query(client)
+                    .map_err(|e| e.to_string())
+            });
+            match variables {
+                Ok(variables) => findings.extend(audit_variables(&scope, &expected, variables)),
+                Err(reason) => {
+                    log::error!("Failed to get variables of {project}: {reason}");
+                    findings.push(VariableFinding {
+                        scope: scope.clone(),
+                        variable: String::new(),
+                        problem: VariableProblem::Unreadable {
+                            reason: reason.clone(),
+                        },
+                    });
+                }
+            }
+        }
+        findings
+    }
+
+    pub(crate) fn variable_audit_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("CI/CD variables").show(ui, |ui| {
+            if ui.button("Audit variables").clicked() {
+                self.variable_audit = Some(self.audit_ci_variables());
+            }
+            match &self.variable_audit {
+                Some(findings) if findings.is_empty() => {
+                    ui.label("All written variables match.");
+                }
+                Some(findings) => {
+                    for finding in findings {
+                        let color = match finding.problem {
+                            VariableProblem::Unmanaged => ui.visuals().text_color(),
+                            _ => ui.visuals().warn_fg_color,
+                        };
+                        ui.colored_label(color, finding.to_string());
+                    }
+                }
+                None => {}
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::Matcher;
+
+    use super::{VariableFinding, VariableProblem, VariableScope};
+    use crate::{
+        gitlab_variable::VariableType, loader::test_config_file, settings::test_client,
+        ConfigAnalyzer, ProjectKey,
+    };
+
+    #[test]
+    pub fn variables_are_compared_with_the_project() {
+        let mut server = mockito::Server::new();
+        let client = test_client(&mut server);
+        let host = server.host_with_port();
+        server
+            .mock("GET", "/api/v4/projects/42/variables")
+            .match_query(Matcher::Any)
+            .with_body(
+                r#"[{"key": "DB_PASSWORD", "value": "x", "variable_type": "env_var",
+                     "protected": true, "masked": false, "environment_scope": "*"},
+                    {"key": "KUBECONFIG", "value": "x", "variable_type": "env_var",
+                     "protected": false, "masked": false, "environment_scope": "production"},
+                    {"key": "KUBECONFIG", "value": "x", "variable_type": "file",
+                     "protected": false, "masked": false, "environment_scope": "*"},
+                    {"key": "LEGACY", "value": "x", "variable_type": "env_var",
+                     "protected": false, "masked": false, "environment_scope": "production"}]"#,
+            )
+            .create();
+
+        let mut app = ConfigAnalyzer::default();
+        app.clients.insert(host.clone(), client);
+        app.project_configs.insert(
+            ProjectKey::new(&host, 1),
+            vec![test_config_file(
+                "cli-config-deploy.json",
+                r#"[{"source": {"type": "Environment"},
+                    "target": {"type": "GitlabProjectVariables",
+                               "config": {"project_id": 42, "environment": "production"},
+                               "details": {"protected_variables": ["DB_PASSWORD"],
+                                           "masked_variables": ["DB_PASSWORD"],
+                                           "files": ["KUBECONFIG"]}},
+                    "mapping": {"DB_PASSWORD": ["DB_PASSWORD"], "KUBECONFIG": ["KUBECONFIG"],
+                                "DB_USER": ["DB_USER"]}}]"#,
+            )],
+        );
+
+        let scope = VariableScope {
+            project: ProjectKey::new(&host, 42),
+            environment: Some("production".to_owned()),
+        };
+        let finding = |variable: &str, problem| VariableFinding {
+            scope: scope.clone(),
+            variable: variable.to_owned(),
+            problem,
+        };
+        assert_eq!(
+            app.audit_ci_variables(),
+            vec![
+                finding("DB_PASSWORD", VariableProblem::NotMasked),
+                finding("DB_USER", VariableProblem::Missing),
+                // the variable of the environment hides the file for all environments
+                finding(
+                    "KUBECONFIG",
+                    VariableProblem::WrongType {
+                        expected: VariableType::File,
+                        actual: VariableType::EnvVar
+                    }
+                ),
+                finding("LEGACY", VariableProblem::Unmanaged),
+            ]
+        );
+
+        // a project without access fails the audit instead of being skipped
+        let forbidden = server
+            .mock("GET", "/api/v4/projects/43/variables")
+            .match_query(Matcher::Any)
+            .with_status(403)
+            .with_body(r#"{"message": "403 Forbidden"}"#)
+            .expect(1)
+            .create();
+        app.project_configs.insert(
+            ProjectKey::new(&host, 2),
+            vec![test_config_file(
+                "cli-config-deploy.json",
+                r#"[{"source": {"type": "Environment"},
+                    "target": {"type": "GitlabProjectVariables",
+                               "config": {"project_id": 43, "environment": "production"}},
+                    "mapping": {"A": ["A"]}},
+                   {"source": {"type": "Environment"},
+                    "target": {"type": "GitlabProjectVariables",
+                               "config": {"project_id": 43, "environment": "staging"}},
+                    "mapping": {"A": ["A"]}}]"#,
+            )],
+        );
+        let unreadable: Vec<_> = app
+            .audit_ci_variables()
+            .into_iter()
+            .filter(|f| matches!(f.problem, VariableProblem::Unreadable { .. }))
+            .map(|f| f.scope.environment)
+            .collect();
+        assert_eq!(
+            unreadable,
+            vec![Some("production".to_owned()), Some("staging".to_owned())]
+        );
+        forbidden.assert();
+    }
+}
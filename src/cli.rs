@@ -0,0 +1,275 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    audit::VariableProblem,
+    diff::SnapshotDiff,
+    graph::{Store, StoreKind},
+    impact::ImpactQuery,
+    merge_request::{parse_change, Change},
+    policy::Policy,
+    report,
+    session::Session,
+    ConfigAnalyzer, ProjectKey,
+};
+
+/// Analyzes how GitLab projects share configuration. Starts the GUI without a command.
+#[derive(Parser, Debug)]
+#[command(version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Prints the projects, tasks and variables affected by a change as JSON.
+    Impact {
+        /// Session file with the analysis.
+        #[arg(long)]
+        session: PathBuf,
+        /// The changed project, as host#id.
+        #[arg(long, conflicts_with = "store")]
+        project: Option<ProjectKey>,
+        /// Kind of the changed store.
+        #[arg(long, requires = "store")]
+        kind: Option<StoreKind>,
+        /// The changed store, e.g. a Key Vault URL or Redis hostname.
+        #[arg(long, requires = "kind")]
+        store: Option<String>,
+        /// The changed key of the store.
+        #[arg(long, requires = "store")]
+        key: Option<String>,
+    },
+    /// Prints the configuration changes between two session files as Markdown.
+    Diff {
+        /// Session file of the earlier analysis.
+        base: PathBuf,
+        /// Session file of the later analysis.
+        head: PathBuf,
+        /// Print JSON instead of Markdown.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Prints the dependency and lint changes of a merge request or branch as Markdown.
+    ///
+    /// Fails if the configuration files can't be read or if the changes introduce invalid
+    /// configurations, Terraform output problems, unresolved variables, dependency cycles,
+    /// exposed sensitive values or secrets of major severity.
+    MergeRequest {
+        /// Session file with the analysis of the main branch, including the instances.
+        #[arg(long)]
+        session: PathBuf,
+        /// The project of the merge request, as host#id.
+        #[arg(long)]
+        project: ProjectKey,
+        /// The merge request iid, or a branch name.
+        change: String,
+        /// Print JSON instead of Markdown.
+        #[arg(long)]
+        json: bool,
+        /// Write the new problems as a GitLab Code Quality report to this file.
+        #[arg(long)]
+        code_quality: Option<PathBuf>,
+        /// Post the report as a note on the merge request, updating the note of an earlier run.
+        #[arg(long)]
+        note: bool,
+    },
+    /// Prints the mappings of projects wired in some of their environments but not in others.
+    Parity {
+        /// Session file with the analysis.
+        #[arg(long)]
+        session: PathBuf,
+        /// Print JSON instead of one gap per line.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Checks the configuration files against a policy, printing one violation per line.
+    ///
+    /// Fails if there are violations.
+    Policy {
+        /// Session file with the analysis.
+        #[arg(long)]
+        session: PathBuf,
+        /// The policy file, JSON.
+        #[arg(long)]
+        policy: PathBuf,
+        /// Print JSON instead of one violation per line.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Compares the CI/CD variables the tasks write with the variables of the projects.
+    ///
+    /// Fails if a written variable is missing or configured differently, or if the variables
+    /// of a project can't be read.
+    AuditVariables {
+        /// Session file with the analysis, including the instances.
+        #[arg(long)]
+        session: PathBuf,
+        /// Print JSON instead of one finding per line.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Runs a command without the GUI.
+pub fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Impact {
+            session,
+            project,
+            kind,
+            store,
+            key,
+        } => {
+            let analyzer = open_session(&session)?;
+            let query = match (project, kind.zip(store), key) {
+                (Some(project), _, _) => ImpactQuery::Project { project },
+                (None, Some((kind, store)), None) => ImpactQuery::Store {
+                    store: Store::new(kind, &store),
+                },
+                (None, Some((kind, store)), Some(key)) => ImpactQuery::Key {
+                    store: Store::new(kind, &store),
+                    key,
+                },
+                (None, None, _) => return Err("either --project or --store is required".into()),
+            };
+            print_json(&analyzer.impact(query))
+        }
+        Command::Diff { base, head, json } => {
+            let diff = SnapshotDiff::new(
+                &base.display().to_string(),
+                &open_session(&base)?,
+                &head.display().to_string(),
+                &open_session(&head)?,
+            );
+            print_diff(&diff, json)
+        }
+        Command::MergeRequest {
+            session,
+            project,
+            change,
+            json,
+            code_quality,
+            note,
+        } => {
+            let mut analyzer = open_session(&session)?;
+            connect(&mut analyzer)?;
+            let change = parse_change(&change);
+            let diff = analyzer.analyze_change(&project, &change)?;
+            print_diff(&diff, json)?;
+            if let Some(path) = code_quality {
+                let report = serde_json::to_string_pretty(&report::code_quality(&diff, &project))
+                    .map_err(|e| e.to_string())?;
+                fs::write(&path, report)
+                    .map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+            }
+            if note {
+                let Change::MergeRequest(iid) = change else {
+                    return Err("notes can only be posted on merge requests".to_owned());
+                };
+                let client = &analyzer.clients[&*project.host];
+                report::post_summary_note(client, project.id, iid, &diff.to_markdown())
+                    .map_err(|e| format!("failed to post note on !{iid}: {e}"))?;
+            }
+            if diff.is_blocking() {
+                return Err(
+                    "The changes introduce invalid configurations, unresolved variables, dependency cycles or secrets"
+                        .to_owned(),
+                );
+            }
+            Ok(())
+        }
+        Command::Parity { session, json } => {
+            let gaps = open_session(&session)?.environment_parity();
+            if json {
+                print_json(&gaps)
+            } else {
+                for gap in &gaps {
+                    println!("{gap}");
+                }
+                Ok(())
+            }
+        }
+        Command::Policy {
+            session,
+            policy,
+            json,
+        } => {
+            let analyzer = open_session(&session)?;
+            let violations = analyzer.policy_violations(&Policy::open(&policy)?);
+            if json {
+                print_json(&violations)?;
+            } else {
+                for violation in &violations {
+                    println!("{violation}");
+                }
+            }
+            if !violations.is_empty() {
+                return Err(format!("{} policy violations", violations.len()));
+            }
+            Ok(())
+        }
+        Command::AuditVariables { session, json } => {
+            let mut analyzer = open_session(&session)?;
+            connect(&mut analyzer)?;
+            let findings = analyzer.audit_ci_variables();
+            if json {
+                print_json(&findings)?;
+            } else {
+                for finding in &findings {
+                    println!("{finding}");
+                }
+            }
+            if findings
+                .iter()
+                .any(|finding| finding.problem != VariableProblem::Unmanaged)
+            {
+                return Err("Written CI/CD variables differ from the projects".to_owned());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Connects to the instances of the session, failing if any of them cannot be reached.
+fn connect(analyzer: &mut ConfigAnalyzer) -> Result<(), String> {
+    analyzer.connect();
+    let errors: Vec<String> = analyzer
+        .connection_status
+        .iter()
+        .filter_map(|(name, status)| status.as_ref().err().map(|e| format!("{name}: {e}")))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("failed to connect to {}", errors.join(", ")))
+    }
+}
+
+fn print_diff(diff: &SnapshotDiff, json: bool) -> Result<(), String> {
+    if json {
+        print_json(diff)
+    } else {
+        print!("{}", diff.to_markdown());
+        Ok(())
+    }
+}
+
+fn open_session(path: &Path) -> Result<ConfigAnalyzer, String> {
+    let session = Session::open(path)
+        .map_err(|e| format!("failed to open session {}: {e}", path.display()))?;
+    let mut analyzer = ConfigAnalyzer::default();
+    analyzer.restore_session(session);
+    Ok(analyzer)
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{json}");
+    Ok(())
+}
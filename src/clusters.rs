@@ -0,0 +1,174 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use eframe::{
+    egui::{self, Color32, Painter, Rect, Shape, Stroke},
+    epaint::{ecolor::Hsva, CircleShape, FontFamily, FontId, Pos2, TextShape},
+};
+use egui_graphs::{Graph, Metadata, Node};
+use petgraph::Directed;
+
+use crate::{project_group, ProjectNode};
+
+const BOX_PADDING: f32 = 12.;
+
+/// FNV-1a hash of `text`, the same on every run unlike std's randomly seeded hasher.
+pub fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// A stable color for a group, the same on every run.
+pub fn group_color(group: &str) -> Color32 {
+    let hash = stable_hash(group);
+    let hue = (hash % 360) as f32 / 360.;
+    Hsva::new(hue, 0.65, 0.9, 1.).into()
+}
+
+/// Shapes of a node colored by its group. The flag tells whether they belong on the top layer.
+pub fn node_shapes(
+    ctx: &egui::Context,
+    n: &Node<ProjectNode>,
+    meta: &Metadata,
+    radius: f32,
+    labels_always: bool,
+) -> (Vec<Shape>, bool) {
+    let is_interacted = n.selected() || n.dragged();
+    let center = n.screen_location(meta).to_pos2();
+    let radius = if is_interacted { radius * 1.5 } else { radius };
+    let fill = match n.data() {
+        Some(data) if !is_interacted => group_color(&data.group),
+        _ => n.color(ctx),
+    };
+    let stroke = Stroke::new(1., n.color(ctx));
+    // stores are squares to tell them apart from projects
+    let mut shapes = if n.data().is_some_and(ProjectNode::is_store) {
+        let rect = Rect::from_center_size(center, egui::vec2(radius, radius) * 2.);
+        vec![
+            Shape::rect_filled(rect, 2., fill),
+            Shape::rect_stroke(rect, 2., stroke),
+        ]
+    } else {
+        vec![Shape::Circle(CircleShape {
+            center,
+            radius,
+            fill,
+            stroke,
+        })]
+    };
+    if labels_always || is_interacted {
+        let galley = ctx.fonts(|f| {
+            f.layout_no_wrap(
+                n.label(),
+                FontId::new(radius, FontFamily::Monospace),
+                ctx.style().visuals.text_color(),
+            )
+        });
+        let position = Pos2::new(center.x, center.y - radius * 2.);
+        shapes.push(Shape::Text(TextShape::new(position, galley)));
+    }
+    (shapes, is_interacted)
+}
+
+/// A custom node draw function using [node_shapes].
+///
+/// This is a macro because egui_graphs does not export the types of the draw function
+/// parameters, so only an inferred closure can be passed.
+macro_rules! group_node_draw {
+    ($labels_always:expr) => {
+        |ctx, n, state, layers| {
+            let radius = n.screen_radius(state.meta, state.style);
+            let (shapes, top) =
+                $crate::clusters::node_shapes(ctx, n, state.meta, radius, $labels_always);
+            for shape in shapes {
+                if top {
+                    layers.add_top(shape);
+                } else {
+                    layers.add(shape);
+                }
+            }
+        }
+    };
+}
+pub(crate) use group_node_draw;
+
+/// Draws a labelled box around the nodes of every group.
+pub fn draw_group_boxes(
+    painter: &Painter,
+    graph: &Graph<ProjectNode, (), Directed>,
+    meta: &Metadata,
+) {
+    let mut bounds: BTreeMap<&str, Rect> = BTreeMap::new();
+    for (_, node) in graph.nodes_iter() {
+        let Some(data) = node.data() else {
+            continue;
+        };
+        let position = node.screen_location(meta).to_pos2();
+        bounds
+            .entry(&data.group)
+            .and_modify(|rect| rect.extend_with(position))
+            .or_insert_with(|| Rect::from_min_max(position, position));
+    }
+    for (group, rect) in bounds {
+        let color = group_color(group);
+        let rect = rect.expand(BOX_PADDING * meta.zoom.max(0.5));
+        painter.rect_stroke(rect, 4., Stroke::new(1., color));
+        painter.text(
+            rect.left_top(),
+            egui::Align2::LEFT_BOTTOM,
+            group,
+            FontId::proportional(12.),
+            color,
+        );
+    }
+}
+
+impl super::ConfigAnalyzer {
+    /// Legend of the group colors and the group cluster options.
+    pub(crate) fn groups_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Groups").show(ui, |ui| {
+            ui.checkbox(&mut self.view.color_by_group, "Color by group");
+            ui.checkbox(&mut self.view.group_boxes, "Box groups");
+            let mut changed = ui
+                .checkbox(
+                    &mut self.view.inter_group_only,
+                    "Only inter-group dependencies",
+                )
+                .changed();
+
+            let groups: BTreeSet<&str> = self.data.values().map(project_group).collect();
+            for group in groups {
+                ui.horizontal(|ui| {
+                    let (rect, _) =
+                        ui.allocate_exact_size(egui::vec2(12., 12.), egui::Sense::hover());
+                    ui.painter().rect_filled(rect, 2., group_color(group));
+                    let mut collapsed = self.view.collapsed_groups.contains(group);
+                    if ui.checkbox(&mut collapsed, group).changed() {
+                        if collapsed {
+                            self.view.collapsed_groups.insert(group.to_owned());
+                        } else {
+                            self.view.collapsed_groups.remove(group);
+                        }
+                        changed = true;
+                    }
+                });
+            }
+            ui.label("Checked groups are collapsed into one node.");
+
+            if changed && !self.data.is_empty() {
+                self.generate_graph();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::group_color;
+
+    #[test]
+    pub fn group_colors_are_stable() {
+        assert_eq!(group_color("platform/infra"), group_color("platform/infra"));
+        assert_ne!(group_color("platform/infra"), group_color("platform/apps"));
+    }
+}
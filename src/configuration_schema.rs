@@ -1,25 +1,48 @@
-use std::{collections::HashMap, fmt::Display, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    path::PathBuf,
+};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
 pub enum AzureKeyvaultSecretType {
     #[serde(rename = "secret")]
     Secret,
     #[serde(rename = "certificate")]
     Certificate,
-    // Key, Will only be supported in the future, if we want to support encryption
+    /// A cryptographic key, e.g. for encryption shared between projects.
+    #[serde(rename = "key")]
+    Key,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+impl AzureKeyvaultSecretType {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AzureKeyvaultSecretType::Secret => "secret",
+            AzureKeyvaultSecretType::Certificate => "certificate",
+            AzureKeyvaultSecretType::Key => "key",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
 pub struct AzureKeyvaultConfig {
     #[serde(rename = "url")]
     pub keyvault_url: String,
     #[serde(rename = "secretType")]
     pub secret_type: AzureKeyvaultSecretType,
+    /// Hint of the format of written values, e.g. `application/x-pem-file`.
+    #[serde(
+        rename = "contentType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub content_type: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
 #[serde(tag = "type")]
 pub enum SourceConfig {
     Environment {},
@@ -39,7 +62,26 @@ pub enum SourceConfig {
     },
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+impl Display for SourceConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceConfig::Environment {} => write!(f, "Environment"),
+            SourceConfig::TerraformFile(c) => write!(f, "TerraformFile {}", c.file_name.display()),
+            SourceConfig::GitlabProjectTerraformState(c) => {
+                write!(f, "GitlabProjectTerraformState {c}")
+            }
+            SourceConfig::GitlabProjectVariables(c) => write!(f, "GitlabProjectVariables {c}"),
+            SourceConfig::EnvFile { file } => write!(f, "EnvFile {}", file.display()),
+            SourceConfig::AzureKeyvault(c) => write!(f, "AzureKeyvault {c}"),
+            SourceConfig::HardCoded { variables } => {
+                write!(f, "HardCoded ({} variables)", variables.len())
+            }
+            SourceConfig::Redis { hostname, .. } => write!(f, "Redis {hostname}"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
 pub struct GitlabProjectConfig {
     pub project_id: u64,
     pub environment: Option<String>,
@@ -48,7 +90,30 @@ pub struct GitlabProjectConfig {
     pub url: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+impl Display for GitlabProjectConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "project {}", self.project_id)?;
+        if let Some(url) = &self.url {
+            write!(f, " on {url}")?;
+        }
+        if let Some(environment) = &self.environment {
+            write!(f, " ({environment})")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for AzureKeyvaultConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}", self.keyvault_url, self.secret_type.label())?;
+        if let Some(content_type) = &self.content_type {
+            write!(f, ", {content_type}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
 #[serde(tag = "type")]
 pub enum TargetConfig {
     Command {},
@@ -71,6 +136,25 @@ pub enum TargetConfig {
     },
 }
 
+impl Display for TargetConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetConfig::Command {} => write!(f, "Command"),
+            TargetConfig::ProcessEnvironment {} => write!(f, "ProcessEnvironment"),
+            TargetConfig::AzureKeyvault(c) => write!(f, "AzureKeyvault {c}"),
+            TargetConfig::GlobalEnvironment {} => write!(f, "GlobalEnvironment"),
+            TargetConfig::StdOutEnvironment {} => write!(f, "StdOutEnvironment"),
+            TargetConfig::EnvFile { file } => write!(f, "EnvFile {}", file.display()),
+            TargetConfig::File => write!(f, "File"),
+            TargetConfig::KubeConfig => write!(f, "KubeConfig"),
+            TargetConfig::GitlabProjectVariables { config, .. } => {
+                write!(f, "GitlabProjectVariables {config}")
+            }
+            TargetConfig::Redis { hostname, .. } => write!(f, "Redis {hostname}"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
 pub struct GitlabProjectVariableDetails {
     pub protected_variables: Option<Vec<String>>,
@@ -78,7 +162,7 @@ pub struct GitlabProjectVariableDetails {
     pub files: Option<Vec<String>>,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct Task {
     pub source: SourceConfig,
     pub target: TargetConfig,
@@ -98,36 +182,134 @@ impl Display for Task {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 #[serde(untagged)]
 pub enum MappingTarget {
     KeyOnly(String),
     ConvertMapping(ConvertMapping), // with #[serde(untagged)] the Value with more attributes (sharing attributes with another value) must come first. Otherwise it will not be used at all. :/
     CopyMapping(CopyMapping),
 }
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+impl MappingTarget {
+    /// The key the value is stored under in the target.
+    pub fn key(&self) -> &str {
+        match self {
+            MappingTarget::KeyOnly(k) => k,
+            MappingTarget::ConvertMapping(c) => &c.key,
+            MappingTarget::CopyMapping(c) => &c.key,
+        }
+    }
+}
+
+impl Display for MappingTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MappingTarget::ConvertMapping(c) => write!(f, "{} ({})", c.key, c.function),
+            _ => write!(f, "{}", self.key()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct CopyMapping {
     pub key: String,
 }
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct ConvertMapping {
     pub key: String,
     pub function: String,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct VariableShareConfig {
     pub version: semver::Version,
     pub tasks: Vec<Task>,
+    /// Keys or fingerprints of values which look like secrets but are not, `*` and `?` in keys
+    /// matching any.
+    #[serde(
+        rename = "secretAllowlist",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub secret_allowlist: Vec<String>,
+    /// Values of `${NAME}` placeholders by environment, `*` holding those of all environments.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl VariableShareConfig {
+    /// Problems the tool would reject or silently ignore, one message each.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (index, task) in self.tasks.iter().enumerate() {
+            let task_number = index + 1;
+            if let SourceConfig::AzureKeyvault(_) = &task.source {
+                for key in task.mapping.keys() {
+                    let (name, version) = split_secret_version(key);
+                    if !is_valid_secret_name(name) {
+                        problems.push(format!("Task {task_number}: invalid Key Vault name {name}"));
+                    }
+                    if version.is_some_and(|v| !is_valid_secret_version(v)) {
+                        problems.push(format!("Task {task_number}: invalid version in {key}"));
+                    }
+                }
+            }
+            if let TargetConfig::AzureKeyvault(c) = &task.target {
+                for target in task.mapping.values().flatten() {
+                    let (name, version) = split_secret_version(target.key());
+                    if version.is_some() {
+                        problems.push(format!(
+                            "Task {task_number}: a version can't be pinned when writing {name}"
+                        ));
+                    }
+                    if !is_valid_secret_name(name) {
+                        problems.push(format!("Task {task_number}: invalid Key Vault name {name}"));
+                    }
+                }
+                if c.secret_type != AzureKeyvaultSecretType::Secret && c.content_type.is_some() {
+                    problems.push(format!(
+                        "Task {task_number}: content types only apply to secrets, not {}s",
+                        c.secret_type.label()
+                    ));
+                }
+            }
+        }
+        problems
+    }
+}
+
+/// The `type` a source or target is written with in the configuration.
+pub fn type_name<T: Serialize>(config: &T) -> String {
+    serde_json::to_value(config)
+        .ok()
+        .and_then(|value| value["type"].as_str().map(str::to_owned))
+        .unwrap_or_default()
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+/// Splits a Key Vault reference `name/version` into name and pinned version.
+pub fn split_secret_version(key: &str) -> (&str, Option<&str>) {
+    match key.split_once('/') {
+        Some((name, version)) => (name, Some(version)),
+        None => (key, None),
+    }
+}
+
+/// Key Vault object names are 1 to 127 alphanumerics and dashes.
+fn is_valid_secret_name(name: &str) -> bool {
+    (1..=127).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Key Vault versions are 32 hex digits.
+fn is_valid_secret_version(version: &str) -> bool {
+    version.len() == 32 && version.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
 pub enum TerraformInputFileFormat {
     OutputJson,
     State,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
 pub struct TerraformInputConfig {
     pub file_name: PathBuf,
     pub file_format: TerraformInputFileFormat,
@@ -140,3 +322,36 @@ impl Default for TerraformInputConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{split_secret_version, VariableShareConfig};
+
+    #[test]
+    pub fn keys_with_pinned_versions() {
+        let config: VariableShareConfig = serde_json::from_str(
+            r#"{"version": "1.0.0", "tasks": [
+                {"source": {"type": "AzureKeyvault", "url": "https://kv.vault.azure.net", "secretType": "key"},
+                 "target": {"type": "AzureKeyvault", "url": "https://other.vault.azure.net", "secretType": "key", "contentType": "text/plain"},
+                 "mapping": {"enc-key/0123456789abcdef0123456789abcdef": ["enc-key"], "bad_name/1": ["copy/1"]}}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            split_secret_version("enc-key/0123456789abcdef0123456789abcdef"),
+            ("enc-key", Some("0123456789abcdef0123456789abcdef"))
+        );
+
+        let mut problems = config.validate();
+        problems.sort();
+        assert_eq!(
+            problems,
+            vec![
+                "Task 1: a version can't be pinned when writing copy",
+                "Task 1: content types only apply to secrets, not keys",
+                "Task 1: invalid Key Vault name bad_name",
+                "Task 1: invalid version in bad_name/1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Display},
+    fs,
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+const KEYRING_SERVICE: &str = "config-analyzer";
+
+/// A GitLab access token.
+///
+/// Deliberately neither `Display`, `Serialize` nor a revealing `Debug`, so it cannot end up
+/// in logs or session files by accident.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Token(String);
+
+impl Token {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self(token.into().trim().to_owned())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Debug for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Token(***)")
+    }
+}
+
+#[derive(Debug)]
+pub enum TokenError {
+    Missing(String),
+    Io(PathBuf, std::io::Error),
+    Keyring(keyring::Error),
+    Glab(PathBuf, serde_yaml::Error),
+}
+
+impl Display for TokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenError::Missing(reason) => write!(f, "no token found: {reason}"),
+            TokenError::Io(path, e) => write!(f, "failed to read {}: {e}", path.display()),
+            TokenError::Keyring(e) => write!(f, "keyring: {e}"),
+            TokenError::Glab(path, e) => {
+                write!(f, "invalid glab config {}: {e}", path.display())
+            }
+        }
+    }
+}
+
+/// Where the token of an instance comes from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum TokenSource {
+    Environment {
+        variable: String,
+    },
+    File {
+        path: PathBuf,
+    },
+    /// The secret service on Linux, the platform keychain elsewhere.
+    Keyring,
+    /// The `glab` CLI configuration, `config.yml` in its default location if unset.
+    Glab {
+        config: Option<PathBuf>,
+    },
+    /// Entered in the settings panel and only kept in memory.
+    Prompt,
+}
+
+impl Default for TokenSource {
+    fn default() -> Self {
+        TokenSource::Environment {
+            variable: "GITLAB_TOKEN".to_owned(),
+        }
+    }
+}
+
+impl TokenSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TokenSource::Environment { .. } => "Environment",
+            TokenSource::File { .. } => "File",
+            TokenSource::Keyring => "Keyring",
+            TokenSource::Glab { .. } => "glab",
+            TokenSource::Prompt => "Prompt",
+        }
+    }
+}
+
+/// Strips scheme and trailing slash from a GitLab URL and lowercases it, so hosts can be
+/// compared.
+pub fn normalize_host(url: &str) -> String {
+    let url = url.trim();
+    let url = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(url);
+    url.trim_end_matches('/').to_lowercase()
+}
+
+/// A named GitLab instance with its own token.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Instance {
+    pub name: String,
+    pub url: String,
+    pub token_source: TokenSource,
+    /// Whether the instance takes part in the analysis.
+    pub enabled: bool,
+    /// Groups to analyze, either by numeric ID or by full path.
+    pub groups: Vec<String>,
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self {
+            name: "gitlab.com".to_owned(),
+            url: "gitlab.com".to_owned(),
+            token_source: TokenSource::default(),
+            enabled: true,
+            groups: Vec::new(),
+        }
+    }
+}
+
+impl Instance {
+    /// The hostname the gitlab client expects.
+    pub fn host(&self) -> String {
+        normalize_host(&self.url)
+    }
+
+    /// Adds a group unless it is blank or already configured.
+    pub fn add_group(&mut self, group: &str) -> bool {
+        let group = group.trim().trim_matches('/');
+        if group.is_empty() || self.groups.iter().any(|g| g == group) {
+            return false;
+        }
+        self.groups.push(group.to_owned());
+        true
+    }
+
+    /// Resolves the token, `prompt` being the value typed into the settings panel.
+    pub fn token(&self, prompt: &str) -> Result<Token, TokenError> {
+        let token = match &self.token_source {
+            TokenSource::Environment { variable } => std::env::var(variable)
+                .map(Token::new)
+                .map_err(|_| TokenError::Missing(format!("{variable} is not set")))?,
+            TokenSource::File { path } => fs::read_to_string(path)
+                .map(Token::new)
+                .map_err(|e| TokenError::Io(path.clone(), e))?,
+            TokenSource::Keyring => self
+                .keyring_entry()?
+                .get_password()
+                .map(Token::new)
+                .map_err(TokenError::Keyring)?,
+            TokenSource::Glab { config } => {
+                let path = config.clone().unwrap_or_else(glab_config_path);
+                let content =
+                    fs::read_to_string(&path).map_err(|e| TokenError::Io(path.clone(), e))?;
+                glab_token(&content, &self.host())
+                    .map_err(|e| TokenError::Glab(path, e))?
+                    .ok_or_else(|| {
+                        TokenError::Missing(format!("no glab host entry for {}", self.host()))
+                    })?
+            }
+            TokenSource::Prompt => Token::new(prompt),
+        };
+        if token.is_empty() {
+            return Err(TokenError::Missing(format!(
+                "empty token for instance {}",
+                self.name
+            )));
+        }
+        Ok(token)
+    }
+
+    /// Stores `token` in the keyring entry of this instance.
+    pub fn store_in_keyring(&self, token: &Token) -> Result<(), TokenError> {
+        self.keyring_entry()?
+            .set_password(token.expose())
+            .map_err(TokenError::Keyring)
+    }
+
+    /// The keyring user of the token, the host so renaming the instance keeps the token.
+    fn keyring_user(&self) -> String {
+        self.host()
+    }
+
+    fn keyring_entry(&self) -> Result<keyring::Entry, TokenError> {
+        keyring::Entry::new(KEYRING_SERVICE, &self.keyring_user()).map_err(TokenError::Keyring)
+    }
+}
+
+#[derive(Deserialize)]
+struct GlabConfig {
+    #[serde(default)]
+    hosts: BTreeMap<String, GlabHost>,
+}
+
+#[derive(Deserialize)]
+struct GlabHost {
+    token: Option<String>,
+    api_host: Option<String>,
+}
+
+/// Default location of the glab configuration, honoring `GLAB_CONFIG_DIR` and `XDG_CONFIG_HOME`.
+fn glab_config_path() -> PathBuf {
+    if let Ok(dir) = std::env::var("GLAB_CONFIG_DIR") {
+        return PathBuf::from(dir).join("config.yml");
+    }
+    let config_home = std::env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(".config")
+        });
+    config_home.join("glab-cli").join("config.yml")
+}
+
+fn glab_token(content: &str, host: &str) -> Result<Option<Token>, serde_yaml::Error> {
+    let config: GlabConfig = serde_yaml::from_str(content)?;
+    Ok(config
+        .hosts
+        .into_iter()
+        .find(|(name, entry)| {
+            name.eq_ignore_ascii_case(host)
+                || entry
+                    .api_host
+                    .as_deref()
+                    .is_some_and(|api_host| api_host.eq_ignore_ascii_case(host))
+        })
+        .and_then(|(_, entry)| entry.token)
+        .map(Token::new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glab_token, Instance, Token, TokenSource};
+
+    #[test]
+    pub fn host_strips_scheme_and_slash() {
+        let instance = Instance {
+            url: " https://gitlab.example.com/ ".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(instance.host(), "gitlab.example.com");
+
+        let renamed = Instance {
+            name: "renamed".to_owned(),
+            url: "https://GitLab.example.com".to_owned(),
+            ..instance.clone()
+        };
+        assert_eq!(renamed.keyring_user(), instance.keyring_user());
+    }
+
+    #[test]
+    pub fn add_group_ignores_duplicates() {
+        let mut instance = Instance::default();
+        assert!(instance.add_group("platform/infra/"));
+        assert!(!instance.add_group("platform/infra"));
+        assert!(!instance.add_group("  "));
+        assert_eq!(instance.groups, vec!["platform/infra".to_owned()]);
+    }
+
+    #[test]
+    pub fn token_is_not_revealed() {
+        let token = Token::new("glpat-secret\n");
+        assert_eq!(token.expose(), "glpat-secret");
+        assert_eq!(format!("{token:?}"), "Token(***)");
+    }
+
+    #[test]
+    pub fn glab_config_lookup() {
+        let config = r#"
+git_protocol: ssh
+hosts:
+    gitlab.com:
+        token: glpat-public
+        api_host: gitlab.com
+    git.example.com:
+        token: glpat-example
+        api_host: api.git.example.com
+"#;
+        let token = glab_token(config, "api.git.example.com").unwrap().unwrap();
+        assert_eq!(token.expose(), "glpat-example");
+        assert!(glab_token(config, "other.com").unwrap().is_none());
+    }
+
+    #[test]
+    pub fn token_from_file() {
+        let path =
+            std::env::temp_dir().join(format!("config-analyzer-token-{}", std::process::id()));
+        std::fs::write(&path, "glpat-file\n").unwrap();
+        let instance = Instance {
+            token_source: TokenSource::File { path: path.clone() },
+            ..Default::default()
+        };
+        assert_eq!(instance.token("").unwrap().expose(), "glpat-file");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
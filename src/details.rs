@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+
+use eframe::egui;
+
+use crate::{graph::Reason, lint::terraform_output_problems, NodeKey, ProjectKey};
+
+impl super::ConfigAnalyzer {
+    /// Projects whose nodes are selected in the graph.
+    pub(crate) fn selected_projects(&self) -> Vec<ProjectKey> {
+        let Some(graph) = &self.graph else {
+            return Vec::new();
+        };
+        graph
+            .g
+            .node_weights()
+            .filter(|node| node.selected())
+            .filter_map(|node| node.data().and_then(|data| data.project_key()).cloned())
+            .collect()
+    }
+
+    /// Keys of the selected nodes of the graph.
+    pub(crate) fn selected_nodes(&self) -> Vec<NodeKey> {
+        let Some(graph) = &self.graph else {
+            return Vec::new();
+        };
+        graph
+            .g
+            .node_weights()
+            .filter(|node| node.selected())
+            .filter_map(|node| node.data().map(|data| data.key.clone()))
+            .collect()
+    }
+
+    /// Configuration, tasks and dependencies of a project.
+    pub(crate) fn details_ui(&self, ui: &mut egui::Ui, key: &ProjectKey) {
+        let Some(project) = self.data.get(key) else {
+            ui.label(format!("Unknown project {key}"));
+            return;
+        };
+        ui.heading(&project.name_with_namespace);
+        ui.hyperlink_to(key.to_string(), &project.web_url);
+
+        let files = self
+            .project_configs
+            .get(key)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if files.is_empty() {
+            ui.label("No configuration files loaded.");
+        }
+        for file in files {
+            ui.separator();
+            let environment = file
+                .environment
+                .as_ref()
+                .map(|environment| format!(" [{environment}]"))
+                .unwrap_or_default();
+            ui.hyperlink_to(
+                format!("{} @ {}{environment}", file.path, file.ref_),
+                format!("{}/-/blob/{}/{}", project.web_url, file.ref_, file.path),
+            );
+            if let Some(error) = &file.load_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            for error in &file.template_errors {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            for problem in file.config.validate() {
+                ui.colored_label(ui.visuals().warn_fg_color, problem);
+            }
+            for problem in terraform_output_problems(key, file) {
+                ui.colored_label(ui.visuals().warn_fg_color, problem.to_string());
+            }
+            for secret in &file.secrets {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("Possible secret: {secret}"),
+                );
+            }
+            egui::Grid::new(format!("tasks {key} {}{environment}", file.path))
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Source");
+                    ui.strong("Target");
+                    ui.strong("Mapping");
+                    ui.end_row();
+                    for task in &file.config.tasks {
+                        ui.label(task.source.to_string());
+                        ui.label(task.target.to_string());
+                        ui.vertical(|ui| {
+                            let mapping: BTreeMap<_, _> = task.mapping.iter().collect();
+                            for (source_key, targets) in mapping {
+                                let targets: Vec<_> =
+                                    targets.iter().map(ToString::to_string).collect();
+                                ui.label(format!("{source_key} → {}", targets.join(", ")));
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+        }
+
+        let flows: Vec<_> = self
+            .sensitive_flows()
+            .into_iter()
+            .filter(|flow| flow.project == *key)
+            .collect();
+        if !flows.is_empty() {
+            ui.separator();
+            ui.strong("Exposed sensitive values");
+            for flow in flows {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!(
+                        "{} from {} → {} {} ({})",
+                        flow.key, flow.origin, flow.target, flow.target_key, flow.file
+                    ),
+                );
+            }
+        }
+
+        ui.separator();
+        ui.strong("Depends on");
+        for dependency in self.project_dependencies.iter().filter(|d| &d.to == key) {
+            self.dependency_ui(ui, &dependency.from, &dependency.reasons);
+        }
+        ui.strong("Used by");
+        for dependency in self.project_dependencies.iter().filter(|d| &d.from == key) {
+            self.dependency_ui(ui, &dependency.to, &dependency.reasons);
+        }
+    }
+
+    fn dependency_ui(&self, ui: &mut egui::Ui, other: &ProjectKey, reasons: &[Reason]) {
+        ui.collapsing(self.project_name(other), |ui| {
+            for reason in reasons {
+                ui.label(reason.to_string());
+            }
+        });
+    }
+}
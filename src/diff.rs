@@ -0,0 +1,605 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use eframe::egui;
+use gitlab::Project;
+use serde::Serialize;
+
+use crate::{
+    lint::{cycle_name, ConfigProblem, TerraformOutputProblem, UnresolvedVariable},
+    loader::ConfigFile,
+    report::Severity,
+    secrets::SecretFinding,
+    sensitivity::SensitiveFlow,
+    session::{session_path, Session},
+    ConfigAnalyzer, ProjectKey,
+};
+
+/// Changes of the dependency model between two snapshots of an analysis.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    pub base: String,
+    pub head: String,
+    pub added_projects: Vec<String>,
+    pub removed_projects: Vec<String>,
+    pub added_dependencies: Vec<String>,
+    pub removed_dependencies: Vec<String>,
+    pub changed_tasks: Vec<TaskChange>,
+    pub new_unresolved: Vec<UnresolvedVariable>,
+    pub new_cycles: Vec<String>,
+    pub new_secrets: Vec<SecretFinding>,
+    pub new_sensitive_flows: Vec<SensitiveFlow>,
+    pub new_config_problems: Vec<ConfigProblem>,
+    pub new_terraform_problems: Vec<TerraformOutputProblem>,
+}
+
+/// Mappings added to or removed from a task, identified by its file, source and target.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TaskChange {
+    pub project: ProjectKey,
+    pub file: String,
+    pub task: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl SnapshotDiff {
+    pub fn new(
+        base_name: &str,
+        base: &ConfigAnalyzer,
+        head_name: &str,
+        head: &ConfigAnalyzer,
+    ) -> Self {
+        // projects are compared by key, names are only for output
+        let (added_projects, removed_projects) =
+            added_removed(&project_keys(base), &project_keys(head));
+        let added_projects = added_projects
+            .iter()
+            .map(|key| head.project_name(key))
+            .collect();
+        let removed_projects = removed_projects
+            .iter()
+            .map(|key| base.project_name(key))
+            .collect();
+        let (added_dependencies, removed_dependencies) =
+            added_removed(&dependency_keys(base), &dependency_keys(head));
+        let added_dependencies = added_dependencies
+            .iter()
+            .map(|dependency| dependency_name(head, dependency))
+            .collect();
+        let removed_dependencies = removed_dependencies
+            .iter()
+            .map(|dependency| dependency_name(base, dependency))
+            .collect();
+
+        let (base_tasks, head_tasks) = (task_mappings(base), task_mappings(head));
+        let changed_tasks = base_tasks
+            .keys()
+            .chain(head_tasks.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|id| {
+                let empty = BTreeSet::new();
+                let (added, removed) = added_removed(
+                    base_tasks.get(id).unwrap_or(&empty),
+                    head_tasks.get(id).unwrap_or(&empty),
+                );
+                let (project, file, task) = id.clone();
+                (!added.is_empty() || !removed.is_empty()).then_some(TaskChange {
+                    project,
+                    file,
+                    task,
+                    added,
+                    removed,
+                })
+            })
+            .collect();
+
+        let base_unresolved = base.unresolved_variables();
+        let new_unresolved = head
+            .unresolved_variables()
+            .into_iter()
+            .filter(|u| !base_unresolved.contains(u))
+            .collect();
+        let (new_cycles, _) = added_removed(&cycle_names(base), &cycle_names(head));
+        let base_flows = base.sensitive_flows();
+        let new_sensitive_flows = head
+            .sensitive_flows()
+            .into_iter()
+            .filter(|f| !base_flows.contains(f))
+            .collect();
+        let base_problems = base.config_problems();
+        let new_config_problems = head
+            .config_problems()
+            .into_iter()
+            .filter(|p| !base_problems.contains(p))
+            .collect();
+        let base_terraform = base.terraform_output_problems();
+        let new_terraform_problems = head
+            .terraform_output_problems()
+            .into_iter()
+            .filter(|p| !base_terraform.contains(p))
+            .collect();
+        let base_secrets = base.secret_findings();
+        let new_secrets = head
+            .secret_findings()
+            .into_iter()
+            .filter(|s| !base_secrets.contains(s))
+            .collect();
+
+        Self {
+            base: base_name.to_owned(),
+            head: head_name.to_owned(),
+            added_projects,
+            removed_projects,
+            added_dependencies,
+            removed_dependencies,
+            changed_tasks,
+            new_unresolved,
+            new_cycles,
+            new_secrets,
+            new_sensitive_flows,
+            new_config_problems,
+            new_terraform_problems,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_projects.is_empty()
+            && self.removed_projects.is_empty()
+            && self.added_dependencies.is_empty()
+            && self.removed_dependencies.is_empty()
+            && self.changed_tasks.is_empty()
+            && self.new_unresolved.is_empty()
+            && self.new_cycles.is_empty()
+            && self.new_secrets.is_empty()
+            && self.new_sensitive_flows.is_empty()
+            && self.new_config_problems.is_empty()
+            && self.new_terraform_problems.is_empty()
+    }
+
+    /// Whether the head introduces problems which should stop it from being merged.
+    pub fn is_blocking(&self) -> bool {
+        !self.new_unresolved.is_empty()
+            || !self.new_cycles.is_empty()
+            || !self.new_sensitive_flows.is_empty()
+            || !self.new_config_problems.is_empty()
+            || !self.new_terraform_problems.is_empty()
+            || self
+                .new_secrets
+                .iter()
+                .any(|secret| secret.severity() >= Severity::Major)
+    }
+
+    /// A report to post in a merge request.
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!(
+            "## Configuration changes `{}` → `{}`\n\n",
+            self.base, self.head
+        );
+        if self.is_empty() {
+            md.push_str("No changes.\n");
+            return md;
+        }
+        let mut list = |title: &str, items: &mut dyn Iterator<Item = String>| {
+            let items: Vec<String> = items.collect();
+            if !items.is_empty() {
+                let _ = writeln!(md, "### {title}\n");
+                for item in items {
+                    let _ = writeln!(md, "- {item}");
+                }
+                md.push('\n');
+            }
+        };
+        list("Added projects", &mut self.added_projects.iter().cloned());
+        list(
+            "Removed projects",
+            &mut self.removed_projects.iter().cloned(),
+        );
+        list(
+            "Added dependencies",
+            &mut self.added_dependencies.iter().cloned(),
+        );
+        list(
+            "Removed dependencies",
+            &mut self.removed_dependencies.iter().cloned(),
+        );
+        list(
+            "Changed mappings",
+            &mut self.changed_tasks.iter().map(|change| {
+                let mut item = format!("`{}` {}: {}", change.file, change.project, change.task);
+                for added in &change.added {
+                    let _ = write!(item, "\n  - ➕ `{added}`");
+                }
+                for removed in &change.removed {
+                    let _ = write!(item, "\n  - ➖ `{removed}`");
+                }
+                item
+            }),
+        );
+        list(
+            "⚠️ New unresolved variables",
+            &mut self.new_unresolved.iter().map(|u| u.to_string()),
+        );
+        list(
+            "⚠️ New dependency cycles",
+            &mut self.new_cycles.iter().cloned(),
+        );
+        list(
+            "⚠️ New configuration problems",
+            &mut self.new_config_problems.iter().map(|p| p.to_string()),
+        );
+        list(
+            "⚠️ New Terraform output problems",
+            &mut self.new_terraform_problems.iter().map(|p| p.to_string()),
+        );
+        list(
+            "⚠️ New exposed sensitive values",
+            &mut self.new_sensitive_flows.iter().map(|f| f.to_string()),
+        );
+        list(
+            "⚠️ New possible secrets",
+            &mut self
+                .new_secrets
+                .iter()
+                .map(|s| format!("{:?}: {s}", s.severity())),
+        );
+        md
+    }
+}
+
+fn added_removed<T: Ord + Clone>(base: &BTreeSet<T>, head: &BTreeSet<T>) -> (Vec<T>, Vec<T>) {
+    (
+        head.difference(base).cloned().collect(),
+        base.difference(head).cloned().collect(),
+    )
+}
+
+fn project_keys(analysis: &ConfigAnalyzer) -> BTreeSet<ProjectKey> {
+    analysis
+        .data
+        .keys()
+        .chain(analysis.project_configs.keys())
+        .cloned()
+        .collect()
+}
+
+fn cycle_names(analysis: &ConfigAnalyzer) -> BTreeSet<String> {
+    analysis
+        .dependency_cycles()
+        .iter()
+        .map(|cycle| cycle_name(cycle))
+        .collect()
+}
+
+fn dependency_keys(analysis: &ConfigAnalyzer) -> BTreeSet<(ProjectKey, ProjectKey)> {
+    analysis
+        .project_dependencies
+        .iter()
+        .map(|d| (d.from.clone(), d.to.clone()))
+        .collect()
+}
+
+fn dependency_name(analysis: &ConfigAnalyzer, (from, to): &(ProjectKey, ProjectKey)) -> String {
+    format!(
+        "{} → {}",
+        analysis.project_name(from),
+        analysis.project_name(to)
+    )
+}
+
+/// Mappings written as `source → target` by project, file and task.
+fn task_mappings(
+    analysis: &ConfigAnalyzer,
+) -> BTreeMap<(ProjectKey, String, String), BTreeSet<String>> {
+    let mut tasks: BTreeMap<_, BTreeSet<String>> = BTreeMap::new();
+    for (key, files) in &analysis.project_configs {
+        for file in files {
+            for task in &file.config.tasks {
+                let id = (
+                    key.clone(),
+                    file.path.clone(),
+                    format!("{} → {}", task.source, task.target),
+                );
+                tasks
+                    .entry(id)
+                    .or_default()
+                    .extend(task.mapping.iter().flat_map(|(source, targets)| {
+                        targets
+                            .iter()
+                            .map(move |target| format!("{source} → {target}"))
+                    }));
+            }
+        }
+    }
+    tasks
+}
+
+impl ConfigAnalyzer {
+    /// An analysis of the given projects and configuration files, without a graph.
+    pub(crate) fn analysis(
+        data: BTreeMap<ProjectKey, Project>,
+        project_configs: BTreeMap<ProjectKey, Vec<ConfigFile>>,
+    ) -> Self {
+        let mut analysis = Self {
+            data,
+            project_configs,
+            ..Default::default()
+        };
+        analysis.update_project_dependencies();
+        analysis
+    }
+
+    /// The name with namespace of a loaded project, its key otherwise.
+    pub(crate) fn project_name(&self, key: &ProjectKey) -> String {
+        self.data
+            .get(key)
+            .map(|p| p.name_with_namespace.clone())
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// Compares the configuration of the loaded projects at two refs.
+    pub(crate) fn diff_refs(&self, base: &str, head: &str) -> SnapshotDiff {
+        let base_analysis = Self::analysis(self.data.clone(), self.load_configs_at(base));
+        let head_analysis = Self::analysis(self.data.clone(), self.load_configs_at(head));
+        SnapshotDiff::new(base, &base_analysis, head, &head_analysis)
+    }
+
+    pub(crate) fn diff_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Compare").show(ui, |ui| {
+            ui.label("Current analysis against session");
+            for name in self.session_names() {
+                if ui.button(format!("Compare with {name}")).clicked() {
+                    let Ok(path) = session_path(&self.settings.sessions_dir, &name) else {
+                        continue;
+                    };
+                    match Session::open(&path) {
+                        Ok(session) => {
+                            let base = Self::analysis(session.data, session.project_configs);
+                            self.diff = Some(SnapshotDiff::new(&name, &base, "current", self));
+                        }
+                        Err(e) => log::error!("Failed to open session {}: {e}", path.display()),
+                    }
+                }
+            }
+            if !self.clients.is_empty() {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Base ref:");
+                    ui.text_edit_singleline(&mut self.compare_refs.0);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Head ref:");
+                    ui.text_edit_singleline(&mut self.compare_refs.1);
+                });
+                if ui.button("Compare refs").clicked() {
+                    let (base, head) = self.compare_refs.clone();
+                    self.diff = Some(self.diff_refs(base.trim(), head.trim()));
+                }
+            }
+        });
+    }
+
+    pub(crate) fn diff_window_ui(&mut self, ctx: &egui::Context) {
+        let Some(diff) = &self.diff else {
+            return;
+        };
+        let mut open = true;
+        egui::Window::new(format!("{} → {}", diff.base, diff.head))
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                if ui.button("Copy Markdown report").clicked() {
+                    ui.output_mut(|o| o.copied_text = diff.to_markdown());
+                }
+                if diff.is_empty() {
+                    ui.label("No changes.");
+                }
+                ui.columns(2, |columns| {
+                    columns[0].heading(&diff.base);
+                    columns[1].heading(&diff.head);
+                    side_by_side(
+                        columns,
+                        "Projects",
+                        &diff.removed_projects,
+                        &diff.added_projects,
+                    );
+                    side_by_side(
+                        columns,
+                        "Dependencies",
+                        &diff.removed_dependencies,
+                        &diff.added_dependencies,
+                    );
+                    for change in &diff.changed_tasks {
+                        let title = format!("{} {}: {}", change.project, change.file, change.task);
+                        side_by_side(columns, &title, &change.removed, &change.added);
+                    }
+                });
+                if !diff.new_unresolved.is_empty() {
+                    ui.separator();
+                    ui.strong("New unresolved variables");
+                    for unresolved in &diff.new_unresolved {
+                        ui.colored_label(ui.visuals().warn_fg_color, unresolved.to_string());
+                    }
+                }
+                if !diff.new_cycles.is_empty() {
+                    ui.separator();
+                    ui.strong("New dependency cycles");
+                    for cycle in &diff.new_cycles {
+                        ui.colored_label(ui.visuals().warn_fg_color, cycle);
+                    }
+                }
+                if !diff.new_config_problems.is_empty() {
+                    ui.separator();
+                    ui.strong("New configuration problems");
+                    for problem in &diff.new_config_problems {
+                        ui.colored_label(ui.visuals().warn_fg_color, problem.to_string());
+                    }
+                }
+                if !diff.new_terraform_problems.is_empty() {
+                    ui.separator();
+                    ui.strong("New Terraform output problems");
+                    for problem in &diff.new_terraform_problems {
+                        ui.colored_label(ui.visuals().warn_fg_color, problem.to_string());
+                    }
+                }
+                if !diff.new_sensitive_flows.is_empty() {
+                    ui.separator();
+                    ui.strong("New exposed sensitive values");
+                    for flow in &diff.new_sensitive_flows {
+                        ui.colored_label(ui.visuals().warn_fg_color, flow.to_string());
+                    }
+                }
+                if !diff.new_secrets.is_empty() {
+                    ui.separator();
+                    ui.strong("New possible secrets");
+                    for secret in &diff.new_secrets {
+                        ui.colored_label(ui.visuals().warn_fg_color, secret.to_string());
+                    }
+                }
+            });
+        if !open {
+            self.diff = None;
+        }
+    }
+}
+
+/// Removed items on the left, added items on the right.
+fn side_by_side(columns: &mut [egui::Ui], title: &str, removed: &[String], added: &[String]) {
+    if removed.is_empty() && added.is_empty() {
+        return;
+    }
+    for column in columns.iter_mut() {
+        column.separator();
+        column.strong(title);
+    }
+    for item in removed {
+        columns[0].colored_label(egui::Color32::LIGHT_RED, format!("- {item}"));
+    }
+    for item in added {
+        columns[1].colored_label(egui::Color32::LIGHT_GREEN, format!("+ {item}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::SnapshotDiff;
+    use crate::{loader::test_config_file, ConfigAnalyzer, ProjectKey};
+
+    #[test]
+    pub fn diff_reports_mapping_and_unresolved_changes() {
+        let writer = test_config_file(
+            "cli-config-writer.json",
+            r#"[{"source": {"type": "Environment"},
+                "target": {"type": "Redis", "hostname": "cache"},
+                "mapping": {"URL": ["url"]}}]"#,
+        );
+        let reader = |mapping: &str| {
+            test_config_file(
+                "cli-config-reader.json",
+                &format!(
+                    r#"[{{"source": {{"type": "Redis", "hostname": "cache"}},
+                        "target": {{"type": "ProcessEnvironment"}},
+                        "mapping": {mapping}}}]"#
+                ),
+            )
+        };
+        let (a, b) = (
+            ProjectKey::new("gitlab.com", 1),
+            ProjectKey::new("gitlab.com", 2),
+        );
+        let base = ConfigAnalyzer::analysis(
+            BTreeMap::new(),
+            BTreeMap::from([(a.clone(), vec![writer.clone()])]),
+        );
+        let head = ConfigAnalyzer::analysis(
+            BTreeMap::new(),
+            BTreeMap::from([
+                (a, vec![writer]),
+                (b, vec![reader(r#"{"url": ["URL"], "token": ["TOKEN"]}"#)]),
+            ]),
+        );
+
+        let diff = SnapshotDiff::new("main", &base, "feature", &head);
+        assert_eq!(diff.added_projects, vec!["gitlab.com#2"]);
+        assert_eq!(diff.added_dependencies, vec!["gitlab.com#1 → gitlab.com#2"]);
+        assert_eq!(diff.changed_tasks.len(), 1);
+        assert_eq!(
+            diff.changed_tasks[0].added,
+            vec!["token → TOKEN", "url → URL"]
+        );
+        assert_eq!(diff.new_unresolved.len(), 1);
+        assert_eq!(diff.new_unresolved[0].key, "token");
+
+        let markdown = diff.to_markdown();
+        assert!(markdown.starts_with("## Configuration changes `main` → `feature`"));
+        assert!(markdown.contains("### ⚠️ New unresolved variables"));
+        assert!(SnapshotDiff::new("main", &head, "main", &head).is_empty());
+
+        // a file which can't be read any more blocks instead of looking removed
+        let mut broken = head.project_configs.clone();
+        let file = &mut broken.get_mut(&ProjectKey::new("gitlab.com", 2)).unwrap()[0];
+        file.config.tasks.clear();
+        file.load_error = Some("Content of cli-config-reader.json is not valid".to_owned());
+        let broken = ConfigAnalyzer::analysis(BTreeMap::new(), broken);
+        let diff = SnapshotDiff::new("main", &head, "feature", &broken);
+        assert_eq!(diff.new_config_problems.len(), 1);
+        assert!(diff.is_blocking());
+
+        // as does a placeholder without a parameter
+        let mut unresolved = head.project_configs.clone();
+        unresolved
+            .get_mut(&ProjectKey::new("gitlab.com", 2))
+            .unwrap()[0]
+            .template_errors
+            .push("Unresolved placeholder ${TIER} in environment prod".to_owned());
+        let unresolved = ConfigAnalyzer::analysis(BTreeMap::new(), unresolved);
+        let diff = SnapshotDiff::new("main", &head, "feature", &unresolved);
+        assert_eq!(
+            diff.new_config_problems[0].message,
+            "Unresolved placeholder ${TIER} in environment prod"
+        );
+        assert!(diff.is_blocking());
+    }
+
+    #[test]
+    pub fn selected_environment_does_not_change_the_diff() {
+        let file = |name: &str, written: &str, read: &str| {
+            vec![test_config_file(
+                name,
+                &format!(
+                    r#"[{{"source": {{"type": "Environment"}},
+                        "target": {{"type": "Redis", "hostname": "cache-dev"}},
+                        "mapping": {{"{written}": ["{written}"]}}}},
+                       {{"source": {{"type": "Redis", "hostname": "cache-dev"}},
+                        "target": {{"type": "ProcessEnvironment"}},
+                        "mapping": {{"{read}": ["{read}"]}}}}]"#
+                ),
+            )]
+        };
+        let project_configs = BTreeMap::from([
+            (
+                ProjectKey::new("gitlab.com", 1),
+                file("cli-config-a.json", "A", "B"),
+            ),
+            (
+                ProjectKey::new("gitlab.com", 2),
+                file("cli-config-b.json", "B", "A"),
+            ),
+        ]);
+        let base = ConfigAnalyzer::analysis(BTreeMap::new(), project_configs.clone());
+        let mut head = ConfigAnalyzer::analysis(BTreeMap::new(), project_configs);
+        head.view.environment = Some("prod".to_owned());
+
+        // the environment only scopes the graph
+        assert_eq!(head.project_dependencies.len(), 2);
+        assert!(head.dependencies(&head.scoped_configs()).is_empty());
+        let diff = SnapshotDiff::new("main", &base, "feature", &head);
+        assert!(diff.is_empty());
+        assert!(!diff.is_blocking());
+    }
+}
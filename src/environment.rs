@@ -0,0 +1,361 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configuration_schema::{type_name, SourceConfig, TargetConfig, Task},
+    loader::ConfigFile,
+    matching::glob_match,
+    ProjectKey,
+};
+
+/// An environment recognized by patterns of Key Vault URLs, Redis hostnames and file paths.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EnvironmentPattern {
+    pub name: String,
+    pub patterns: Vec<String>,
+}
+
+impl EnvironmentPattern {
+    fn new(name: &str, patterns: &[&str]) -> Self {
+        Self {
+            name: name.to_owned(),
+            patterns: patterns.iter().map(|p| (*p).to_owned()).collect(),
+        }
+    }
+}
+
+pub fn default_environments() -> Vec<EnvironmentPattern> {
+    vec![
+        EnvironmentPattern::new("dev", &["*dev*"]),
+        EnvironmentPattern::new("staging", &["*staging*", "*stg*"]),
+        EnvironmentPattern::new("prod", &["*prod*", "*prd*"]),
+    ]
+}
+
+/// The environment a task explicitly reads from or writes to.
+fn explicit_environment(task: &Task) -> Option<&str> {
+    let source = match &task.source {
+        SourceConfig::GitlabProjectTerraformState(c) | SourceConfig::GitlabProjectVariables(c) => {
+            c.environment.as_deref()
+        }
+        _ => None,
+    };
+    let target = match &task.target {
+        TargetConfig::GitlabProjectVariables { config, .. } => config.environment.as_deref(),
+        _ => None,
+    };
+    source.or(target)
+}
+
+/// Key Vault URLs and Redis hostnames of a task.
+fn store_names(task: &Task) -> Vec<&str> {
+    let mut names = Vec::new();
+    match &task.source {
+        SourceConfig::AzureKeyvault(c) => names.push(c.keyvault_url.as_str()),
+        SourceConfig::Redis { hostname, .. } => names.push(hostname),
+        _ => {}
+    }
+    match &task.target {
+        TargetConfig::AzureKeyvault(c) => names.push(c.keyvault_url.as_str()),
+        TargetConfig::Redis { hostname, .. } => names.push(hostname),
+        _ => {}
+    }
+    names
+}
+
+fn matching_environment<'a>(environments: &'a [EnvironmentPattern], name: &str) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    environments
+        .iter()
+        .find(|environment| {
+            environment
+                .patterns
+                .iter()
+                .any(|pattern| glob_match(&pattern.to_lowercase(), &name))
+        })
+        .map(|environment| environment.name.as_str())
+}
+
+/// The environment a task of `file` is declared for, without looking at the stores it uses.
+///
+/// An environment field of the task wins over the environment the file was expanded for,
+/// the environment fields of the other tasks and the path of the file. Checks of the stores
+/// a task may use rely on this, so a store can't vouch for itself.
+pub fn declared_environment<'a>(
+    environments: &'a [EnvironmentPattern],
+    file: &'a ConfigFile,
+    task: &'a Task,
+) -> Option<&'a str> {
+    explicit_environment(task)
+        .or(file.environment.as_deref())
+        .or_else(|| file.config.tasks.iter().find_map(explicit_environment))
+        .or_else(|| matching_environment(environments, &file.path))
+}
+
+/// The environment a task of `file` belongs to, None for all environments.
+///
+/// Like [declared_environment], except that the Key Vault URLs and Redis hostnames of the
+/// task win over the environment fields of the other tasks and the path of the file.
+pub fn task_environment<'a>(
+    environments: &'a [EnvironmentPattern],
+    file: &'a ConfigFile,
+    task: &'a Task,
+) -> Option<&'a str> {
+    explicit_environment(task)
+        .or(file.environment.as_deref())
+        .or_else(|| {
+            store_names(task)
+                .into_iter()
+                .find_map(|name| matching_environment(environments, name))
+        })
+        .or_else(|| declared_environment(environments, file, task))
+}
+
+/// Mappings of a project wired in some of its environments but not in others.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ParityGap {
+    pub project: ProjectKey,
+    /// The mapping as `source → target: key → target key`.
+    pub wiring: String,
+    pub present: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+impl Display for ParityGap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} in {} is wired in {} but missing in {}",
+            self.wiring,
+            self.project,
+            self.present.join(", "),
+            self.missing.join(", ")
+        )
+    }
+}
+
+impl super::ConfigAnalyzer {
+    /// The environment of a task of `file`, None for all environments.
+    pub(crate) fn task_environment<'a>(
+        &'a self,
+        file: &'a ConfigFile,
+        task: &'a Task,
+    ) -> Option<&'a str> {
+        task_environment(&self.settings.environments, file, task)
+    }
+
+    /// All environments tasks belong to, sorted.
+    pub(crate) fn environments(&self) -> BTreeSet<String> {
+        self.project_configs
+            .values()
+            .flatten()
+            .flat_map(|file| {
+                file.config
+                    .tasks
+                    .iter()
+                    .filter_map(|task| self.task_environment(file, task))
+            })
+            .map(str::to_owned)
+            .collect()
+    }
+
+    /// The configuration files with only the tasks of the selected environment.
+    ///
+    /// Tasks of all environments are kept.
+    pub(crate) fn scoped_configs(&self) -> Cow<'_, BTreeMap<ProjectKey, Vec<ConfigFile>>> {
+        let Some(environment) = &self.view.environment else {
+            return Cow::Borrowed(&self.project_configs);
+        };
+        let scoped = self
+            .project_configs
+            .iter()
+            .map(|(key, files)| {
+                let files = files
+                    .iter()
+                    .map(|file| {
+                        let mut scoped = file.clone();
+                        scoped.config.tasks.retain(|task| {
+                            self.task_environment(file, task)
+                                .is_none_or(|task_environment| task_environment == environment)
+                        });
+                        scoped
+                    })
+                    .collect();
+                (key.clone(), files)
+            })
+            .collect();
+        Cow::Owned(scoped)
+    }
+
+    /// Mappings wired in some environments of a project but not in all of them.
+    ///
+    /// Only the environments a project has tasks for are compared.
+    pub(crate) fn environment_parity(&self) -> Vec<ParityGap> {
+        let mut gaps = Vec::new();
+        for (project, files) in &self.project_configs {
+            let mut wirings: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+            let mut project_environments = BTreeSet::new();
+            for file in files {
+                for (index, task) in file.config.tasks.iter().enumerate() {
+                    let Some(environment) = self.task_environment(file, task) else {
+                        continue;
+                    };
+                    project_environments.insert(environment);
+                    let flow = format!("{} → {}", type_name(&task.source), type_name(&task.target));
+                    // expanded files are compared by their mappings before the expansion
+                    for (key, targets) in &file.template_task(index).mapping {
+                        for target in targets {
+                            wirings
+                                .entry(format!("{flow}: {key} → {}", target.key()))
+                                .or_default()
+                                .insert(environment);
+                        }
+                    }
+                }
+            }
+            for (wiring, present) in wirings {
+                let missing: Vec<String> = project_environments
+                    .difference(&present)
+                    .map(|e| (*e).to_owned())
+                    .collect();
+                if !missing.is_empty() {
+                    gaps.push(ParityGap {
+                        project: project.clone(),
+                        wiring,
+                        present: present.into_iter().map(str::to_owned).collect(),
+                        missing,
+                    });
+                }
+            }
+        }
+        gaps
+    }
+
+    pub(crate) fn environment_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Environments").show(ui, |ui| {
+            let previous = self.view.environment.clone();
+            egui::ComboBox::from_label("Environment")
+                .selected_text(self.view.environment.as_deref().unwrap_or("All"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.view.environment, None, "All");
+                    for environment in self.environments() {
+                        let label = environment.clone();
+                        ui.selectable_value(&mut self.view.environment, Some(environment), label);
+                    }
+                });
+            if self.view.environment != previous && !self.project_configs.is_empty() {
+                self.generate_graph();
+            }
+
+            ui.label("Patterns of Key Vault URLs, Redis hosts and file paths:");
+            for environment in &mut self.settings.environments {
+                ui.horizontal(|ui| {
+                    ui.label(&environment.name);
+                    let mut patterns = environment.patterns.join(", ");
+                    if ui.text_edit_singleline(&mut patterns).changed() {
+                        environment.patterns = patterns
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|p| !p.is_empty())
+                            .map(str::to_owned)
+                            .collect();
+                    }
+                });
+            }
+
+            let gaps = self.environment_parity();
+            if !gaps.is_empty() {
+                ui.separator();
+                ui.strong("Parity");
+                for gap in gaps {
+                    ui.colored_label(ui.visuals().warn_fg_color, gap.to_string());
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        loader::{test_config_file, test_expanded_files},
+        ConfigAnalyzer, ProjectKey,
+    };
+
+    #[test]
+    pub fn environments_and_parity() {
+        let mut app = ConfigAnalyzer::default();
+        app.project_configs.insert(
+            ProjectKey::new("gitlab.com", 1),
+            vec![
+                test_config_file(
+                    "cli-config-staging.json",
+                    r#"[{"source": {"type": "AzureKeyvault", "url": "https://app-stg.vault.azure.net", "secretType": "secret"},
+                        "target": {"type": "ProcessEnvironment"},
+                        "mapping": {"db-password": ["DB_PASSWORD"], "api-key": ["API_KEY"]}},
+                       {"source": {"type": "Environment"},
+                        "target": {"type": "ProcessEnvironment"},
+                        "mapping": {"HOME": ["HOME"]}}]"#,
+                ),
+                test_config_file(
+                    "cli-config-deploy.json",
+                    r#"[{"source": {"type": "AzureKeyvault", "url": "https://app-prod.vault.azure.net", "secretType": "secret"},
+                        "target": {"type": "GitlabProjectVariables", "config": {"project_id": 1}, "details": null},
+                        "mapping": {"db-password": ["DB_PASSWORD"]}},
+                       {"source": {"type": "AzureKeyvault", "url": "https://app-prod.vault.azure.net", "secretType": "secret"},
+                        "target": {"type": "ProcessEnvironment"},
+                        "mapping": {"db-password": ["DB_PASSWORD"]}}]"#,
+                ),
+            ],
+        );
+
+        assert_eq!(
+            app.environments().into_iter().collect::<Vec<_>>(),
+            vec!["prod", "staging"]
+        );
+        let gaps: Vec<_> = app
+            .environment_parity()
+            .into_iter()
+            .map(|gap| gap.to_string())
+            .collect();
+        assert_eq!(
+            gaps,
+            vec![
+                "AzureKeyvault → GitlabProjectVariables: db-password → DB_PASSWORD in gitlab.com#1 is wired in prod but missing in staging",
+                "AzureKeyvault → ProcessEnvironment: api-key → API_KEY in gitlab.com#1 is wired in staging but missing in prod",
+                "Environment → ProcessEnvironment: HOME → HOME in gitlab.com#1 is wired in staging but missing in prod",
+            ]
+        );
+
+        app.view.environment = Some("prod".to_owned());
+        let scoped = app.scoped_configs();
+        let files = &scoped[&ProjectKey::new("gitlab.com", 1)];
+        assert!(files[0].config.tasks.is_empty());
+        assert_eq!(files[1].config.tasks.len(), 2);
+    }
+
+    #[test]
+    pub fn parity_of_expanded_configs() {
+        let mut app = ConfigAnalyzer::default();
+        app.project_configs.insert(
+            ProjectKey::new("gitlab.com", 1),
+            test_expanded_files(
+                "cli-config.json",
+                r#"{"version": "1.0.0", "parameters": {"dev": {}, "prod": {}},
+                    "tasks": [{"source": {"type": "AzureKeyvault", "url": "https://app-${ENV}.vault.azure.net", "secretType": "secret"},
+                        "target": {"type": "ProcessEnvironment"},
+                        "mapping": {"db-${ENV}": ["DB_PASSWORD"]}}]}"#,
+            ),
+        );
+
+        // db-dev and db-prod are the same template key wired in both environments
+        assert_eq!(app.environments().len(), 2);
+        assert!(app.environment_parity().is_empty());
+    }
+}
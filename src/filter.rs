@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configuration_schema::Task,
+    graph::{task_stores, Dependency, Reason, StoreKind},
+    loader::tasks,
+    project_group, ProjectKey,
+};
+
+/// Restricts the graph to the part of the analysis of interest, persisted with the view.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct GraphFilter {
+    /// Groups to show, all when empty.
+    pub groups: BTreeSet<String>,
+    /// Kinds of stores to show, all when empty.
+    pub store_kinds: BTreeSet<StoreKind>,
+    /// Part of a Key Vault URL or Redis hostname.
+    pub store: String,
+    /// Part of a variable name.
+    pub variable: String,
+    /// Projects whose neighborhood is shown, all projects when empty.
+    pub focus: BTreeSet<ProjectKey>,
+    /// Number of dependency hops shown around `focus`.
+    pub hops: usize,
+}
+
+impl GraphFilter {
+    /// Whether only tasks with a matching store or variable are of interest.
+    fn filters_tasks(&self) -> bool {
+        !self.store_kinds.is_empty() || !self.store.is_empty() || !self.variable.is_empty()
+    }
+
+    /// Whether a store of `kind` called `name` is shown.
+    pub fn matches_store(&self, kind: StoreKind, name: &str) -> bool {
+        (self.store_kinds.is_empty() || self.store_kinds.contains(&kind))
+            && (self.store.is_empty() || contains_ignore_case(name, &self.store))
+    }
+
+    /// Whether a task of the project `owner` uses a matching store and variable.
+    pub fn matches_task(&self, owner: &ProjectKey, task: &Task) -> bool {
+        let store = (self.store_kinds.is_empty() && self.store.is_empty())
+            || task_stores(owner, task)
+                .iter()
+                .any(|store| self.matches_store(store.kind, &store.name));
+        let variable = self.variable.is_empty()
+            || task.mapping.iter().any(|(key, targets)| {
+                contains_ignore_case(key, &self.variable)
+                    || targets
+                        .iter()
+                        .any(|target| contains_ignore_case(target.key(), &self.variable))
+            });
+        store && variable
+    }
+
+    /// Whether a dependency through the store of `reason` is shown.
+    pub fn matches_reason(&self, reason: &Reason) -> bool {
+        self.matches_store(reason.store.kind, &reason.store.name)
+    }
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+/// Projects at most `hops` dependencies away from `focus`, regardless of direction.
+pub fn neighborhood(
+    dependencies: &[Dependency],
+    focus: &BTreeSet<ProjectKey>,
+    hops: usize,
+) -> BTreeSet<ProjectKey> {
+    let mut adjacent: BTreeMap<&ProjectKey, Vec<&ProjectKey>> = BTreeMap::new();
+    for dependency in dependencies {
+        adjacent
+            .entry(&dependency.from)
+            .or_default()
+            .push(&dependency.to);
+        adjacent
+            .entry(&dependency.to)
+            .or_default()
+            .push(&dependency.from);
+    }
+    let mut reached = focus.clone();
+    let mut queue: VecDeque<(&ProjectKey, usize)> = focus.iter().map(|key| (key, 0)).collect();
+    while let Some((key, distance)) = queue.pop_front() {
+        if distance == hops {
+            continue;
+        }
+        for next in adjacent.get(key).into_iter().flatten() {
+            if reached.insert((*next).clone()) {
+                queue.push_back((next, distance + 1));
+            }
+        }
+    }
+    reached
+}
+
+impl super::ConfigAnalyzer {
+    /// Projects passing the filter of the view.
+    pub(crate) fn visible_projects(&self) -> BTreeSet<ProjectKey> {
+        let filter = &self.view.filter;
+        let neighborhood = (!filter.focus.is_empty())
+            .then(|| neighborhood(&self.project_dependencies, &filter.focus, filter.hops));
+        self.data
+            .iter()
+            .filter(|(key, project)| {
+                (filter.groups.is_empty() || filter.groups.contains(project_group(project)))
+                    && neighborhood.as_ref().is_none_or(|n| n.contains(key))
+                    && (!filter.filters_tasks()
+                        || self.project_configs.get(key).is_some_and(|files| {
+                            tasks(files).any(|task| filter.matches_task(key, task))
+                        }))
+            })
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    pub(crate) fn filter_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Filter").show(ui, |ui| {
+            let previous = self.view.filter.clone();
+            let selected = self.selected_projects();
+            let filter = &mut self.view.filter;
+
+            ui.label("Groups");
+            let groups: BTreeSet<&str> = self.data.values().map(project_group).collect();
+            for group in groups {
+                let mut shown = filter.groups.contains(group);
+                if ui.checkbox(&mut shown, group).changed() {
+                    if shown {
+                        filter.groups.insert(group.to_owned());
+                    } else {
+                        filter.groups.remove(group);
+                    }
+                }
+            }
+
+            ui.label("Stores");
+            for kind in StoreKind::ALL {
+                let mut shown = filter.store_kinds.contains(&kind);
+                if ui.checkbox(&mut shown, kind.label()).changed() {
+                    if shown {
+                        filter.store_kinds.insert(kind);
+                    } else {
+                        filter.store_kinds.remove(&kind);
+                    }
+                }
+            }
+            let store = ui.horizontal(|ui| {
+                ui.label("Key Vault URL or Redis host:");
+                ui.text_edit_singleline(&mut filter.store)
+            });
+            let variable = ui.horizontal(|ui| {
+                ui.label("Variable:");
+                ui.text_edit_singleline(&mut filter.variable)
+            });
+            // text is applied on enter or when leaving the field, not on every keystroke
+            let editing = store.inner.has_focus() || variable.inner.has_focus();
+            let submitted = store.inner.lost_focus() || variable.inner.lost_focus();
+
+            ui.horizontal(|ui| {
+                if ui.button("Focus on selection").clicked() {
+                    filter.focus = selected.into_iter().collect();
+                }
+                ui.add(
+                    egui::DragValue::new(&mut filter.hops)
+                        .clamp_range(0..=10)
+                        .suffix(" hops"),
+                );
+            });
+            if !filter.focus.is_empty() {
+                ui.label(format!("Focused on {} projects", filter.focus.len()));
+            }
+            if ui.button("Clear filter").clicked() {
+                *filter = Default::default();
+            }
+
+            let changed = previous != self.view.filter && !editing;
+            if (changed || submitted) && !self.data.is_empty() {
+                self.generate_graph();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::neighborhood;
+    use crate::{graph::Dependency, ProjectKey};
+
+    #[test]
+    pub fn neighborhood_follows_dependencies_both_ways() {
+        let key = |id| ProjectKey::new("gitlab.com", id);
+        let dependency = |from, to| Dependency {
+            from: key(from),
+            to: key(to),
+            reasons: Vec::new(),
+        };
+        let dependencies = [dependency(1, 2), dependency(3, 2), dependency(3, 4)];
+        let focus = BTreeSet::from([key(1)]);
+
+        assert_eq!(neighborhood(&dependencies, &focus, 0), focus);
+        assert_eq!(
+            neighborhood(&dependencies, &focus, 2),
+            BTreeSet::from([key(1), key(2), key(3)])
+        );
+    }
+}
@@ -18,9 +18,12 @@ pub struct File {
 }
 
 impl File {
-    pub fn get_content(&self) -> String {
-        let decoded: Vec<u8> = general_purpose::STANDARD.decode(&self.content).unwrap();
-        String::from_utf8(decoded).unwrap()
+    /// The decoded content, failing if it is not valid base64 encoded UTF-8.
+    pub fn get_content(&self) -> Result<String, String> {
+        let decoded: Vec<u8> = general_purpose::STANDARD
+            .decode(&self.content)
+            .map_err(|e| format!("content is not valid base64: {e}"))?;
+        String::from_utf8(decoded).map_err(|e| format!("content is not valid UTF-8: {e}"))
     }
 }
 
@@ -43,8 +46,14 @@ mod tests {
             "last_commit_id": "570e7b2abdd848b95f2f578043fc23bd6f6fd24d",
             "execute_filemode": false
          }"#;
-        let file: super::File = serde_json::from_str(json).unwrap();
+        let mut file: super::File = serde_json::from_str(json).unwrap();
         assert_eq!(file.file_name, "key.rb");
-        assert_eq!(file.get_content(), "Hello World");
+        assert_eq!(file.get_content().unwrap(), "Hello World");
+
+        // binary content is an error instead of a panic
+        file.content = "/w==".to_owned();
+        assert!(file.get_content().unwrap_err().contains("UTF-8"));
+        file.content = "not base64!".to_owned();
+        assert!(file.get_content().unwrap_err().contains("base64"));
     }
 }
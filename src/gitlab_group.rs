@@ -1,4 +1,12 @@
-use gitlab::{GroupId, GroupStatistics, Project, VisibilityLevel};
+use std::collections::{BTreeMap, BTreeSet};
+
+use gitlab::{
+    api::{
+        groups::{projects::GroupProjects, subgroups::GroupSubgroups, Groups},
+        paged, ApiError, Pagination, Query,
+    },
+    GroupId, GroupStatistics, Project, RestError, VisibilityLevel,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -26,6 +34,240 @@ pub struct Group {
     pub parent_id: Option<GroupId>,
     /// Statistics about the group.
     pub statistics: Option<GroupStatistics>,
+}
+
+/// Filters applied when discovering the projects of a group.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProjectDiscovery {
+    /// Let GitLab include the projects of all subgroups in the listing.
+    pub include_subgroups: bool,
+    /// Additionally walk the subgroup tree and list every subgroup on its own.
+    pub walk_subgroups: bool,
+    pub include_archived: bool,
+    pub include_forks: bool,
+    /// Include projects shared with the group from other namespaces.
+    pub include_shared: bool,
+}
+
+impl Default for ProjectDiscovery {
+    fn default() -> Self {
+        Self {
+            include_subgroups: true,
+            walk_subgroups: false,
+            include_archived: false,
+            include_forks: false,
+            include_shared: false,
+        }
+    }
+}
+
+/// Lists all subgroups below `group`, depth first.
+pub fn subgroups(client: &gitlab::Gitlab, group: &str) -> Result<Vec<Group>, ApiError<RestError>> {
+    let mut visited: BTreeSet<GroupId> = BTreeSet::new();
+    let mut pending: Vec<String> = vec![group.to_owned()];
+    let mut result = Vec::new();
+
+    while let Some(current) = pending.pop() {
+        let endpoint = GroupSubgroups::builder()
+            .group(current.as_str())
+            .build()
+            .unwrap();
+        let children: Vec<Group> = paged(endpoint, Pagination::All).query(client)?;
+        for child in children {
+            // a group can only have one parent, but guard against cycles anyway
+            if visited.insert(child.id) {
+                log::debug!("Subgroup {} of {}", &child.full_path, &current);
+                pending.push(child.id.to_string());
+                result.push(child);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Lists the projects of a single group through the paginated group projects API.
+fn group_projects(
+    client: &gitlab::Gitlab,
+    group: &str,
+    include_subgroups: bool,
+    options: &ProjectDiscovery,
+) -> Result<Vec<Project>, ApiError<RestError>> {
+    let mut builder = GroupProjects::builder();
+    builder
+        .group(group)
+        .include_subgroups(include_subgroups)
+        .with_shared(options.include_shared);
+    if !options.include_archived {
+        builder.archived(false);
+    }
+    paged(builder.build().unwrap(), Pagination::All).query(client)
+}
+
+/// Discovers all projects of `group` according to `options`, keyed by project id.
+pub fn discover_projects(
+    client: &gitlab::Gitlab,
+    group: &str,
+    options: &ProjectDiscovery,
+) -> Result<BTreeMap<Box<str>, Project>, ApiError<RestError>> {
+    let mut projects = group_projects(client, group, options.include_subgroups, options)?;
+
+    if options.walk_subgroups {
+        for subgroup in subgroups(client, group)? {
+            projects.extend(group_projects(
+                client,
+                &subgroup.id.to_string(),
+                false,
+                options,
+            )?);
+        }
+    }
+
+    Ok(projects
+        .into_iter()
+        .filter(|project| options.include_archived || !project.archived)
+        .filter(|project| {
+            let keep = options.include_forks || project.forked_from_project.is_none();
+            if !keep {
+                log::debug!("Skipping fork {}", &project.path_with_namespace);
+            }
+            keep
+        })
+        .map(|project| (project.id.to_string().into_boxed_str(), project))
+        .collect())
+}
+
+/// Lists all groups visible to the current user, used to pick the groups to analyze.
+pub fn available_groups(client: &gitlab::Gitlab) -> Result<Vec<Group>, ApiError<RestError>> {
+    let endpoint = Groups::builder().build().unwrap();
+    paged(endpoint, Pagination::All).query(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::Matcher;
+
+    use super::{discover_projects, subgroups, ProjectDiscovery};
+    use crate::settings::test_client;
+
+    fn group(id: u64, full_path: &str) -> String {
+        format!(
+            r#"{{"id": {id}, "name": "{full_path}", "path": "{full_path}", "description": null,
+                "visibility": "private", "lfs_enabled": false, "avatar_url": null, "web_url": "",
+                "request_access_enabled": false, "full_name": "{full_path}",
+                "full_path": "{full_path}", "parent_id": null, "statistics": null}}"#
+        )
+    }
+
+    fn project(id: u64, archived: bool, fork: bool) -> String {
+        let forked_from = if fork {
+            r#"{"id": 1, "name": "upstream", "name_with_namespace": "other / upstream",
+                "path": "upstream", "path_with_namespace": "other/upstream",
+                "http_url_to_repo": "", "web_url": ""}"#
+        } else {
+            "null"
+        };
+        format!(
+            r#"{{"id": {id}, "description": null, "default_branch": "main", "tag_list": [],
+                "archived": {archived}, "empty_repo": false, "visibility": "private",
+                "ssh_url_to_repo": "", "http_url_to_repo": "", "web_url": "", "readme_url": null,
+                "owner": null, "name": "p{id}", "name_with_namespace": "top / p{id}",
+                "path": "p{id}", "path_with_namespace": "top/p{id}",
+                "created_at": "2023-01-01T00:00:00Z", "last_activity_at": "2023-01-01T00:00:00Z",
+                "shared_runners_enabled": true, "lfs_enabled": false, "creator_id": 1,
+                "namespace": {{"id": 1, "path": "top", "name": "top", "kind": "group",
+                               "full_path": "top", "avatar_url": null, "web_url": ""}},
+                "forked_from_project": {forked_from}, "avatar_url": null, "star_count": 0,
+                "forks_count": 0, "public_jobs": false, "shared_with_groups": [],
+                "request_access_enabled": false, "jobs_enabled": true, "issues_enabled": true,
+                "merge_requests_enabled": true, "snippets_enabled": false, "wiki_enabled": false,
+                "builds_access_level": "enabled", "issues_access_level": "enabled",
+                "merge_requests_access_level": "enabled", "repository_access_level": "enabled",
+                "snippets_access_level": "disabled", "wiki_access_level": "disabled"}}"#
+        )
+    }
+
+    fn list(items: impl IntoIterator<Item = String>) -> String {
+        format!("[{}]", items.into_iter().collect::<Vec<_>>().join(","))
+    }
+
+    #[test]
+    pub fn projects_of_nested_subgroups_are_discovered_and_filtered() {
+        let mut server = mockito::Server::new();
+        let client = test_client(&mut server);
+        let mock = |server: &mut mockito::ServerGuard, path: &str, query: Matcher, body: String| {
+            server
+                .mock("GET", format!("/api/v4/groups/{path}").as_str())
+                .match_query(query)
+                .with_body(body)
+                .create();
+        };
+        mock(
+            &mut server,
+            "top/subgroups",
+            Matcher::Any,
+            list([group(2, "top/a")]),
+        );
+        mock(
+            &mut server,
+            "2/subgroups",
+            Matcher::Any,
+            list([group(3, "top/a/b")]),
+        );
+        mock(&mut server, "3/subgroups", Matcher::Any, list([]));
+        // a full first page makes the client request the second one
+        mock(
+            &mut server,
+            "top/projects",
+            Matcher::UrlEncoded("page".into(), "1".into()),
+            list((100..200).map(|id| project(id, false, false))),
+        );
+        mock(
+            &mut server,
+            "top/projects",
+            Matcher::UrlEncoded("page".into(), "2".into()),
+            list([project(10, false, false), project(11, false, true)]),
+        );
+        mock(
+            &mut server,
+            "2/projects",
+            Matcher::Any,
+            list([project(20, true, false)]),
+        );
+        mock(
+            &mut server,
+            "3/projects",
+            Matcher::Any,
+            list([project(30, false, false)]),
+        );
+
+        let paths: Vec<_> = subgroups(&client, "top")
+            .unwrap()
+            .into_iter()
+            .map(|group| group.full_path)
+            .collect();
+        assert_eq!(paths, vec!["top/a", "top/a/b"]);
+
+        let options = ProjectDiscovery {
+            include_subgroups: false,
+            walk_subgroups: true,
+            ..ProjectDiscovery::default()
+        };
+        let projects = discover_projects(&client, "top", &options).unwrap();
+        assert_eq!(projects.len(), 102);
+        assert!(projects.contains_key("10"));
+        assert!(projects.contains_key("30"));
+        // the fork and the archived project are excluded
+        assert!(!projects.contains_key("11"));
+        assert!(!projects.contains_key("20"));
 
-    pub projects: Option<Vec<Project>>, // TODO: create MR for gitlab crate to add this field
+        let options = ProjectDiscovery {
+            include_forks: true,
+            include_archived: true,
+            ..options
+        };
+        let projects = discover_projects(&client, "top", &options).unwrap();
+        assert!(projects.contains_key("11"));
+        assert!(projects.contains_key("20"));
+    }
 }
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// The parts of a merge request needed to read its changes.
+///
+/// The gitlab crate types require many more fields than needed here.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MergeRequest {
+    pub iid: u64,
+    pub title: String,
+    pub source_branch: String,
+    /// Differs from the target project for merge requests from forks.
+    pub source_project_id: u64,
+    pub target_branch: String,
+    pub web_url: String,
+}
+
+/// A comment on a merge request.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Note {
+    pub id: u64,
+    pub body: String,
+}
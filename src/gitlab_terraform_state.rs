@@ -0,0 +1,26 @@
+use gitlab::api::{common::path_escaped, endpoint_prelude::*};
+
+/// Query the latest version of a GitLab-managed Terraform state.
+///
+/// The gitlab crate does not provide this endpoint. The response is the state document itself.
+#[derive(Debug, Clone)]
+pub struct TerraformStateEndpoint {
+    pub project: u64,
+    pub name: String,
+}
+
+impl Endpoint for TerraformStateEndpoint {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        // https://docs.gitlab.com/ee/user/infrastructure/iac/terraform_state.html
+        format!(
+            "projects/{}/terraform/state/{}",
+            self.project,
+            path_escaped(&self.name)
+        )
+        .into()
+    }
+}
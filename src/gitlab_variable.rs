@@ -0,0 +1,40 @@
+use gitlab::api::endpoint_prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Query the CI/CD variables of a project.
+///
+/// The gitlab crate only provides endpoints for single variables.
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectVariablesEndpoint {
+    pub project: u64,
+}
+
+impl Endpoint for ProjectVariablesEndpoint {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        // https://docs.gitlab.com/ee/api/project_level_variables.html#list-project-variables
+        format!("projects/{}/variables", self.project).into()
+    }
+}
+
+impl Pageable for ProjectVariablesEndpoint {}
+
+/// A CI/CD variable of a project. The value is not kept.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    pub key: String,
+    pub variable_type: VariableType,
+    pub protected: bool,
+    pub masked: bool,
+    pub environment_scope: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableType {
+    EnvVar,
+    File,
+}
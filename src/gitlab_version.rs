@@ -0,0 +1,24 @@
+use gitlab::api::endpoint_prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Query the version of the GitLab instance.
+///
+/// The gitlab crate does not provide this endpoint.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VersionEndpoint;
+
+impl Endpoint for VersionEndpoint {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        "version".into()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Version {
+    pub version: String,
+    pub revision: String,
+}
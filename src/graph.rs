@@ -1,55 +1,110 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Display,
+    path::Path,
+};
 
-use crate::configuration_schema::*;
+use crate::{
+    configuration_schema::*,
+    credentials::normalize_host,
+    filter::GraphFilter,
+    layout::{self, LayoutKind},
+    loader::{tasks, ConfigFile},
+    matching::{
+        match_keys, match_secrets, normalize_vault_url, requested_keys, stored_keys, KeyMatch,
+    },
+};
+use clap::ValueEnum;
 use eframe::epaint::Vec2;
 use egui_graphs::{default_edge_transform, to_graph_custom, Graph, Node};
 use log::warn;
 use petgraph::{prelude::*, EdgeType};
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::{ProjectNode, DEFAULT_SPAWN_SIZE};
+use crate::{project_group, ProjectKey, ProjectNode};
+
+/// How the dependency graph is presented, persisted with the session.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ViewOptions {
+    pub labels_always: bool,
+    pub fit_to_screen: bool,
+    pub zoom_and_pan: bool,
+    pub layout: LayoutKind,
+    /// Seed of the starting positions of the force directed layout.
+    pub layout_seed: u64,
+    pub color_by_group: bool,
+    pub group_boxes: bool,
+    /// Groups shown as a single node.
+    pub collapsed_groups: BTreeSet<String>,
+    /// Hide dependencies between projects of the same group.
+    pub inter_group_only: bool,
+    pub filter: GraphFilter,
+    /// Show stores as nodes projects write to and read from instead of project dependencies.
+    pub stores_as_nodes: bool,
+    /// Only analyze tasks of this environment and of all environments.
+    pub environment: Option<String>,
+}
+
+impl Default for ViewOptions {
+    fn default() -> Self {
+        Self {
+            labels_always: false,
+            fit_to_screen: true,
+            zoom_and_pan: false,
+            layout: LayoutKind::default(),
+            layout_seed: 0,
+            color_by_group: true,
+            group_boxes: false,
+            collapsed_groups: BTreeSet::new(),
+            inter_group_only: false,
+            filter: GraphFilter::default(),
+            stores_as_nodes: false,
+            environment: None,
+        }
+    }
+}
 
 impl super::ConfigAnalyzer {
+    /// Updates the dependencies of all tasks, whatever environment is selected.
     pub(crate) fn update_project_dependencies(&mut self) {
-        self.project_dependencies.clear();
+        self.project_dependencies = self.dependencies(&self.project_configs);
+    }
+
+    /// The dependencies between the projects of `project_configs`.
+    pub(crate) fn dependencies(
+        &self,
+        project_configs: &BTreeMap<ProjectKey, Vec<ConfigFile>>,
+    ) -> Vec<Dependency> {
+        let mut dependencies = Vec::new();
 
         // TODO: next session we will refactor this
-        for (p1_key, config_1) in self.project_configs.iter() {
-            for (p2_key, config_2) in self.project_configs.iter() {
+        for (p1_key, config_1) in project_configs.iter() {
+            for (p2_key, config_2) in project_configs.iter() {
                 if p1_key != p2_key {
-                    let mut found = false;
-                    for task_1 in &config_1.tasks {
-                        for task_2 in &config_2.tasks {
+                    let mut reasons = Vec::new();
+                    for task_1 in tasks(config_1) {
+                        for task_2 in tasks(config_2) {
                             log::debug!(
                                 "Checking {p1_key}.{:#?} -> {p2_key}.{:#?}",
                                 task_1.source,
                                 task_2.target
                             );
+                            // conventions like which sources may be used are checked by the policy
                             match &task_1.source {
-                                SourceConfig::GitlabProjectTerraformState(
-                                    GitlabProjectConfig { project_id, .. },
-                                ) => {
-                                    if project_id.to_string().into_boxed_str() == *p1_key {
-                                        log::info!(
-                                            "GitlabProjectTerraformState usage in same repo {}",
-                                            project_id
-                                        );
-                                    } else {
-                                        log::error!("GitlabProjectTerraformState usage in differnt repo -> {}", project_id);
-                                    }
-                                }
-                                SourceConfig::GitlabProjectVariables(_) => {
-                                    log::error!("GitlabProjectVariables must not be used")
-                                }
-                                SourceConfig::AzureKeyvault(c1) => match &task_2.target {
-                                    TargetConfig::AzureKeyvault(c2) => {
-                                        if c1.keyvault_url == c2.keyvault_url {
-                                            log::info!(
-                                                "AzureKeyvault match {} == {}",
-                                                c1.keyvault_url,
-                                                c2.keyvault_url
-                                            );
-                                            found = true;
+                                SourceConfig::AzureKeyvault(c1) => {
+                                    if let TargetConfig::AzureKeyvault(c2) = &task_2.target {
+                                        let store = keyvault_store(c1);
+                                        if store == keyvault_store(c2) {
+                                            let matches = match_secrets(task_1, task_2);
+                                            if !matches.is_empty() {
+                                                log::info!("AzureKeyvault match {store}");
+                                                reasons.push(Reason::new(store, matches));
+                                            } else {
+                                                log::info!(
+                                                    "AzureKeyvault no matching secrets in {store}"
+                                                );
+                                            }
                                         } else {
                                             log::info!(
                                                 "AzureKeyvault no match {} -> {}",
@@ -58,40 +113,27 @@ impl super::ConfigAnalyzer {
                                             );
                                         }
                                     }
-                                    _ => {}
-                                },
-                                SourceConfig::Redis { hostname, .. } => match &task_2.target {
-                                    TargetConfig::Redis { hostname: h2, .. } => {
+                                }
+                                SourceConfig::Redis { hostname, .. } => {
+                                    if let TargetConfig::Redis { hostname: h2, .. } = &task_2.target
+                                    {
                                         if *hostname == *h2 {
                                             log::info!(
                                                 "Redis host are same {}... checking variables",
                                                 h2
                                             );
-                                            let target_variable_names = &task_2
-                                                .mapping
-                                                .values()
-                                                .map(|v| {
-                                                    v.iter()
-                                                        .map(|v| match v {
-                                                            MappingTarget::KeyOnly(k) => k,
-                                                            MappingTarget::ConvertMapping(c) => {
-                                                                &c.key
-                                                            }
-                                                            MappingTarget::CopyMapping(c) => &c.key,
-                                                        })
-                                                        .collect::<Vec<&String>>()
-                                                })
-                                                .flatten()
-                                                .collect::<Vec<&String>>();
-                                            let source_variable_names =
-                                                &task_1.mapping.keys().collect::<Vec<&String>>();
-                                            use array_tool::vec::Intersect;
-                                            let intersects = source_variable_names
-                                                .intersect(target_variable_names.clone());
-                                            if !intersects.is_empty() {
-                                                found = true;
+                                            let matches = match_keys(task_1, task_2);
+                                            if !matches.is_empty() {
+                                                reasons.push(Reason::new(
+                                                    Store::new(StoreKind::Redis, hostname),
+                                                    matches,
+                                                ));
                                             } else {
-                                                warn!("No intersecting variables for redis: {:?} != {:?}", source_variable_names, target_variable_names);
+                                                warn!(
+                                                    "No matching keys for redis: {:?} != {:?}",
+                                                    requested_keys(task_1).collect::<Vec<_>>(),
+                                                    stored_keys(task_2).collect::<Vec<_>>()
+                                                );
                                             }
                                         } else {
                                             warn!(
@@ -100,33 +142,81 @@ impl super::ConfigAnalyzer {
                                             );
                                         }
                                     }
-                                    _ => {}
-                                },
+                                }
                                 _ => {}
                             }
                         }
                     }
-                    if found {
-                        self.project_dependencies
-                            .push((p2_key.clone(), p1_key.clone()));
+                    for reason in reasons {
+                        add_dependency(&mut dependencies, p2_key, p1_key, reason);
                     }
                 }
             }
         }
+
+        // projects referenced directly, possibly on another instance
+        for (key, config) in project_configs.iter() {
+            for task in tasks(config) {
+                let (from, to, kind) = match (&task.source, &task.target) {
+                    (SourceConfig::GitlabProjectTerraformState(c), _) => (
+                        resolve_project(key, c),
+                        key.clone(),
+                        StoreKind::TerraformState,
+                    ),
+                    (SourceConfig::GitlabProjectVariables(c), _) => (
+                        resolve_project(key, c),
+                        key.clone(),
+                        StoreKind::GitlabVariables,
+                    ),
+                    (_, TargetConfig::GitlabProjectVariables { config: c, .. }) => (
+                        key.clone(),
+                        resolve_project(key, c),
+                        StoreKind::GitlabVariables,
+                    ),
+                    _ => continue,
+                };
+                if from == to {
+                    continue;
+                }
+                let edge = (from, to);
+                if self.data.contains_key(&edge.0) && self.data.contains_key(&edge.1) {
+                    add_dependency(
+                        &mut dependencies,
+                        &edge.0,
+                        &edge.1,
+                        Reason::new(Store::new(kind, &edge.0.to_string()), Vec::new()),
+                    );
+                } else {
+                    warn!("Project reference {} -> {} is not loaded", edge.0, edge.1);
+                }
+            }
+        }
+        dependencies
     }
 
     pub(crate) fn generate_graph(&mut self) {
         // TODO implement dependency graph
         // let mut g: DiGraphMap<ProjectNode, ()> = GraphMap::new();
         let mut g: StableGraph<ProjectNode, ()> = StableGraph::new();
-        let mut nodes: BTreeMap<Box<str>, NodeIndex> = BTreeMap::new();
+        let mut nodes: BTreeMap<ProjectKey, NodeIndex> = BTreeMap::new();
+        let mut collapsed: BTreeMap<&str, Vec<&ProjectKey>> = BTreeMap::new();
+        let visible = self.visible_projects();
 
-        for project in self.data.iter() {
+        for (key, project) in self.data.iter().filter(|(key, _)| visible.contains(key)) {
+            let group = project_group(project);
+            if self.view.collapsed_groups.contains(group) {
+                collapsed.entry(group).or_default().push(key);
+                continue;
+            }
             // let node = g.add_node(());
             // let node = Node::default();
             // let node = node.with_data(Some(project.1));
-            let index = g.add_node(project.1.clone().into());
-            nodes.insert(project.0.clone(), index);
+            let index = g.add_node(ProjectNode::new(key.clone(), project));
+            nodes.insert(key.clone(), index);
+        }
+        for (group, members) in collapsed {
+            let index = g.add_node(ProjectNode::group(group, members.len()));
+            nodes.extend(members.into_iter().map(|key| (key.clone(), index)));
         }
         // let a = g.add_node(ProjectNode {
         //     id: "1".into(),
@@ -139,13 +229,65 @@ impl super::ConfigAnalyzer {
         //     group: "2".into(),
         // });
 
-        for edge in &self.project_dependencies {
-            let (a, b) = (nodes.get(&edge.0).unwrap(), nodes.get(&edge.1).unwrap());
-
-            g.add_edge(*a, *b, ());
+        // edges of collapsed groups are aggregated into one edge per pair of nodes
+        let mut edges = BTreeSet::new();
+        if self.view.stores_as_nodes {
+            let mut stores: BTreeMap<Store, NodeIndex> = BTreeMap::new();
+            for (from, to) in self.store_edges() {
+                let mut index = |end: StoreEdgeEnd, g: &mut StableGraph<ProjectNode, ()>| match end
+                {
+                    StoreEdgeEnd::Project(key) => nodes.get(&key).copied(),
+                    StoreEdgeEnd::Store(store) => self
+                        .view
+                        .filter
+                        .matches_store(store.kind, &store.name)
+                        .then(|| {
+                            *stores
+                                .entry(store.clone())
+                                .or_insert_with(|| g.add_node(ProjectNode::store(store)))
+                        }),
+                };
+                let (Some(a), Some(b)) = (index(from, &mut g), index(to, &mut g)) else {
+                    continue;
+                };
+                if edges.insert((a, b)) {
+                    g.add_edge(a, b, ());
+                }
+            }
+        }
+        // the graph only shows the dependencies of the selected environment
+        let scoped = self
+            .view
+            .environment
+            .is_some()
+            .then(|| self.dependencies(&self.scoped_configs()));
+        for dependency in scoped
+            .as_ref()
+            .unwrap_or(&self.project_dependencies)
+            .iter()
+            .filter(|_| !self.view.stores_as_nodes)
+        {
+            let (Some(&a), Some(&b)) = (nodes.get(&dependency.from), nodes.get(&dependency.to))
+            else {
+                continue;
+            };
+            if !dependency
+                .reasons
+                .iter()
+                .any(|reason| self.view.filter.matches_reason(reason))
+            {
+                continue;
+            }
+            if a == b || self.view.inter_group_only && g[a].group == g[b].group {
+                continue;
+            }
+            if edges.insert((a, b)) {
+                g.add_edge(a, b, ());
+            }
         }
 
-        self.graph = Some(to_input_graph(&g));
+        let locations = layout::compute(&g, self.view.layout, self.view.layout_seed);
+        self.graph = Some(to_input_graph(&g, &locations));
         // println!("{:?}", Dot::with_config(&self.graph, &[Config::EdgeNoLabel]));
         // graph {
         //     0 [label="\"0\""]
@@ -159,18 +301,383 @@ impl super::ConfigAnalyzer {
     }
 }
 
-pub fn random_location(size: f32) -> Vec2 {
-    let mut rng = rand::thread_rng();
-    Vec2::new(rng.gen_range(0. ..size), rng.gen_range(0. ..size))
+/// One end of a writes or reads edge of the store view.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StoreEdgeEnd {
+    Project(ProjectKey),
+    Store(Store),
+}
+
+impl super::ConfigAnalyzer {
+    /// Edges from projects to the stores they write and from stores to the projects reading them.
+    pub(crate) fn store_edges(&self) -> BTreeSet<(StoreEdgeEnd, StoreEdgeEnd)> {
+        let mut edges = BTreeSet::new();
+        for (key, files) in self.scoped_configs().iter() {
+            for task in tasks(files) {
+                if let Some(store) = source_store(key, &task.source) {
+                    // the state is written by the project it belongs to
+                    if let SourceConfig::GitlabProjectTerraformState(c) = &task.source {
+                        edges.insert((
+                            StoreEdgeEnd::Project(resolve_project(key, c)),
+                            StoreEdgeEnd::Store(store.clone()),
+                        ));
+                    }
+                    edges.insert((
+                        StoreEdgeEnd::Store(store),
+                        StoreEdgeEnd::Project(key.clone()),
+                    ));
+                }
+                if let Some(store) = target_store(key, &task.target) {
+                    // variables are read by the pipelines of the project they are set on
+                    if let TargetConfig::GitlabProjectVariables { config, .. } = &task.target {
+                        edges.insert((
+                            StoreEdgeEnd::Store(store.clone()),
+                            StoreEdgeEnd::Project(resolve_project(key, config)),
+                        ));
+                    }
+                    edges.insert((
+                        StoreEdgeEnd::Project(key.clone()),
+                        StoreEdgeEnd::Store(store),
+                    ));
+                }
+            }
+        }
+        edges
+    }
+}
+
+impl super::ConfigAnalyzer {
+    /// Moves the nodes of the current graph to the locations of the selected layout.
+    pub(crate) fn apply_layout(&mut self) {
+        let Some(graph) = self.graph.as_mut() else {
+            return;
+        };
+        // nodes without data are left where they are, the indices of the others are kept
+        let plain = graph
+            .g
+            .filter_map(|_, node| node.data().cloned(), |_, _| Some(()));
+        let locations = layout::compute(&plain, self.view.layout, self.view.layout_seed);
+        for (index, location) in locations {
+            if let Some(node) = graph.node_mut(index) {
+                node.set_location(location);
+            }
+        }
+    }
+}
+
+/// A project using values another project provides.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// The providing project.
+    pub from: ProjectKey,
+    /// The consuming project.
+    pub to: ProjectKey,
+    pub reasons: Vec<Reason>,
+}
+
+/// The kinds of stores projects share values through.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum,
+)]
+pub enum StoreKind {
+    KeyVault,
+    Redis,
+    TerraformState,
+    GitlabVariables,
+    EnvFile,
+}
+
+impl StoreKind {
+    pub const ALL: [StoreKind; 5] = [
+        StoreKind::KeyVault,
+        StoreKind::Redis,
+        StoreKind::TerraformState,
+        StoreKind::GitlabVariables,
+        StoreKind::EnvFile,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StoreKind::KeyVault => "Key Vault",
+            StoreKind::Redis => "Redis",
+            StoreKind::TerraformState => "Terraform state",
+            StoreKind::GitlabVariables => "GitLab variables",
+            StoreKind::EnvFile => "Env file",
+        }
+    }
+}
+
+/// A store values are written to and read from, e.g. a Key Vault by its URL.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Store {
+    pub kind: StoreKind,
+    /// Key Vault URL, Redis hostname or the key of the project holding the state or variables.
+    pub name: String,
+}
+
+impl Store {
+    pub fn new(kind: StoreKind, name: &str) -> Self {
+        Self {
+            kind,
+            name: name.to_owned(),
+        }
+    }
+}
+
+impl Display for Store {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.kind.label(), self.name)
+    }
+}
+
+/// Why one project depends on another: the shared store and the keys passed through it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Reason {
+    pub store: Store,
+    pub keys: Vec<KeyMatch>,
+}
+
+impl Reason {
+    pub fn new(store: Store, keys: Vec<KeyMatch>) -> Self {
+        Self { store, keys }
+    }
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.store)?;
+        if !self.keys.is_empty() {
+            let keys: Vec<String> = self.keys.iter().map(ToString::to_string).collect();
+            write!(f, ": {}", keys.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The stores a task of the project `owner` reads from or writes to.
+pub fn task_stores(owner: &ProjectKey, task: &Task) -> Vec<Store> {
+    source_store(owner, &task.source)
+        .into_iter()
+        .chain(target_store(owner, &task.target))
+        .collect()
+}
+
+/// The store a task of the project `owner` reads from.
+pub fn source_store(owner: &ProjectKey, source: &SourceConfig) -> Option<Store> {
+    match source {
+        SourceConfig::AzureKeyvault(c) => Some(keyvault_store(c)),
+        SourceConfig::Redis { hostname, .. } => Some(Store::new(StoreKind::Redis, hostname)),
+        SourceConfig::GitlabProjectTerraformState(c) => Some(Store::new(
+            StoreKind::TerraformState,
+            &resolve_project(owner, c).to_string(),
+        )),
+        SourceConfig::GitlabProjectVariables(c) => Some(Store::new(
+            StoreKind::GitlabVariables,
+            &resolve_project(owner, c).to_string(),
+        )),
+        SourceConfig::EnvFile { file } => Some(env_file_store(owner, file)),
+        _ => None,
+    }
+}
+
+/// The store a task of the project `owner` writes to.
+pub fn target_store(owner: &ProjectKey, target: &TargetConfig) -> Option<Store> {
+    match target {
+        TargetConfig::AzureKeyvault(c) => Some(keyvault_store(c)),
+        TargetConfig::Redis { hostname, .. } => Some(Store::new(StoreKind::Redis, hostname)),
+        TargetConfig::GitlabProjectVariables { config, .. } => Some(Store::new(
+            StoreKind::GitlabVariables,
+            &resolve_project(owner, config).to_string(),
+        )),
+        TargetConfig::EnvFile { file } => Some(env_file_store(owner, file)),
+        _ => None,
+    }
+}
+
+/// Secrets, certificates and keys of a vault are separate stores, even when named alike.
+pub fn keyvault_store(config: &AzureKeyvaultConfig) -> Store {
+    let url = normalize_vault_url(&config.keyvault_url);
+    match &config.secret_type {
+        AzureKeyvaultSecretType::Secret => Store::new(StoreKind::KeyVault, &url),
+        secret_type => Store::new(
+            StoreKind::KeyVault,
+            &format!("{url} ({})", secret_type.label()),
+        ),
+    }
+}
+
+/// Env files only exist in the pipeline of their project, so they are named by it.
+fn env_file_store(owner: &ProjectKey, file: &Path) -> Store {
+    Store::new(StoreKind::EnvFile, &format!("{owner}:{}", file.display()))
+}
+
+/// Records `reason` on the dependency `from` -> `to`, creating it if needed.
+pub fn add_dependency(
+    dependencies: &mut Vec<Dependency>,
+    from: &ProjectKey,
+    to: &ProjectKey,
+    reason: Reason,
+) {
+    match dependencies
+        .iter_mut()
+        .find(|d| d.from == *from && d.to == *to)
+    {
+        Some(dependency) => {
+            if !dependency.reasons.contains(&reason) {
+                dependency.reasons.push(reason);
+            }
+        }
+        None => dependencies.push(Dependency {
+            from: from.clone(),
+            to: to.clone(),
+            reasons: vec![reason],
+        }),
+    }
+}
+
+/// The project a [GitlabProjectConfig] of a task in `owner` points to.
+///
+/// Without an explicit url the project lives on the same instance as `owner`.
+pub fn resolve_project(owner: &ProjectKey, config: &GitlabProjectConfig) -> ProjectKey {
+    let host = config
+        .url
+        .as_deref()
+        .map(normalize_host)
+        .unwrap_or_else(|| owner.host.to_string());
+    ProjectKey::new(&host, config.project_id)
 }
 
 pub fn to_input_graph<E: Clone, Ty: EdgeType>(
     g: &StableGraph<ProjectNode, E, Ty>,
+    locations: &HashMap<NodeIndex, Vec2>,
 ) -> Graph<ProjectNode, E, Ty> {
-    to_graph_custom(g, projects_node_transform, default_edge_transform)
+    to_graph_custom(
+        g,
+        |idx, data| projects_node_transform(data, locations.get(&idx).copied()),
+        default_edge_transform,
+    )
+}
+
+pub fn projects_node_transform(data: &ProjectNode, location: Option<Vec2>) -> Node<ProjectNode> {
+    Node::new(location.unwrap_or_default(), data.clone()).with_label(data.name.to_string())
 }
 
-pub fn projects_node_transform(_idx: NodeIndex, data: &ProjectNode) -> Node<ProjectNode> {
-    let loc = random_location(DEFAULT_SPAWN_SIZE);
-    Node::new(loc, data.clone()).with_label(data.name.to_string())
+#[cfg(test)]
+mod tests {
+    use super::{resolve_project, Store, StoreEdgeEnd, StoreKind};
+    use crate::{
+        configuration_schema::GitlabProjectConfig, credentials::Instance, loader::test_config_file,
+        ConfigAnalyzer, ProjectKey,
+    };
+
+    #[test]
+    pub fn resolve_project_on_other_instance() {
+        let owner: ProjectKey = "gitlab.com#1".parse().unwrap();
+        let mut config = GitlabProjectConfig {
+            project_id: 42,
+            environment: None,
+            token: None,
+            url: None,
+        };
+        assert_eq!(
+            resolve_project(&owner, &config),
+            ProjectKey::new("gitlab.com", 42)
+        );
+
+        config.url = Some("https://git.example.com/".to_owned());
+        let key = resolve_project(&owner, &config);
+        assert_eq!(key.to_string(), "git.example.com#42");
+        assert_ne!(key, ProjectKey::new("gitlab.com", 42));
+
+        // hosts differing in case are the same instance
+        config.url = Some("https://Git.Example.com".to_owned());
+        assert_eq!(resolve_project(&owner, &config), key);
+        let instance = Instance {
+            url: "https://GIT.example.com/".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(ProjectKey::new(&instance.host(), 42), key);
+    }
+
+    #[test]
+    pub fn store_edges_connect_writers_and_readers() {
+        let (writer, reader) = (
+            ProjectKey::new("gitlab.com", 1),
+            ProjectKey::new("gitlab.com", 2),
+        );
+        let mut app = ConfigAnalyzer::default();
+        app.project_configs.insert(
+            writer.clone(),
+            vec![test_config_file(
+                "cli-config-test.json",
+                r#"[{"source": {"type": "Environment"},
+                    "target": {"type": "AzureKeyvault", "url": "https://kv.vault.azure.net", "secretType": "secret"},
+                    "mapping": {"DB_PASSWORD": ["db-password"]}}]"#,
+            )],
+        );
+        app.project_configs.insert(
+            reader.clone(),
+            vec![test_config_file(
+                "cli-config-test.json",
+                r#"[{"source": {"type": "AzureKeyvault", "url": "https://kv.vault.azure.net", "secretType": "secret"},
+                    "target": {"type": "ProcessEnvironment"},
+                    "mapping": {"db-password": ["DB_PASSWORD"]}}]"#,
+            )],
+        );
+
+        let vault = StoreEdgeEnd::Store(Store::new(
+            StoreKind::KeyVault,
+            "https://kv.vault.azure.net",
+        ));
+        let edges = app.store_edges();
+        assert_eq!(edges.len(), 2);
+        assert!(edges.contains(&(StoreEdgeEnd::Project(writer), vault.clone())));
+        assert!(edges.contains(&(vault, StoreEdgeEnd::Project(reader))));
+    }
+
+    #[test]
+    pub fn keyvault_dependencies_match_secrets() {
+        let key = |id| ProjectKey::new("gitlab.com", id);
+        let reader = |secret_type: &str, secret: &str| {
+            vec![test_config_file(
+                "cli-config-reader.json",
+                &format!(
+                    r#"[{{"source": {{"type": "AzureKeyvault", "url": "https://kv.vault.azure.net", "secretType": "{secret_type}"}},
+                        "target": {{"type": "ProcessEnvironment"}},
+                        "mapping": {{"{secret}": ["VALUE"]}}}}]"#
+                ),
+            )]
+        };
+        let mut app = ConfigAnalyzer::default();
+        app.project_configs.insert(
+            key(1),
+            vec![test_config_file(
+                "cli-config-writer.json",
+                r#"[{"source": {"type": "Environment"},
+                    "target": {"type": "AzureKeyvault", "url": "https://KV.vault.azure.net/", "secretType": "secret"},
+                    "mapping": {"DB_PASSWORD": ["db-password"], "TLS": ["tls"]}}]"#,
+            )],
+        );
+        app.project_configs
+            .insert(key(2), reader("secret", "db-password"));
+        app.project_configs
+            .insert(key(3), reader("certificate", "tls"));
+        app.project_configs
+            .insert(key(4), reader("secret", "other"));
+
+        app.update_project_dependencies();
+        let dependencies: Vec<_> = app
+            .project_dependencies
+            .iter()
+            .map(|d| (d.from.id, d.to.id, d.reasons[0].to_string()))
+            .collect();
+        assert_eq!(
+            dependencies,
+            vec![(
+                1,
+                2,
+                "Key Vault https://kv.vault.azure.net: db-password".to_owned()
+            )]
+        );
+    }
 }
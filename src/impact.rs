@@ -0,0 +1,323 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use eframe::egui;
+use serde::Serialize;
+
+use crate::{
+    graph::{source_store, target_store, Store, StoreKind},
+    matching::store_key_matches,
+    NodeKey, ProjectKey,
+};
+
+/// What is about to change.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum ImpactQuery {
+    /// Everything the project writes.
+    Project { project: ProjectKey },
+    /// All keys of a store.
+    Store { store: Store },
+    /// A single key of a store.
+    Key { store: Store, key: String },
+}
+
+/// Projects, tasks and variables affected by an [ImpactQuery].
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Impact {
+    pub query: ImpactQuery,
+    pub projects: Vec<AffectedProject>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AffectedProject {
+    pub project: ProjectKey,
+    pub name: String,
+    /// Number of stores or dependencies between the change and the project.
+    pub distance: usize,
+    pub tasks: Vec<AffectedTask>,
+}
+
+/// A task reading a changed value.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AffectedTask {
+    pub file: String,
+    pub source: String,
+    pub target: String,
+    /// The store the task reads the changed values from.
+    pub store: Store,
+    /// Keys of the store holding a changed value, sorted.
+    pub keys: Vec<String>,
+    /// Target variables holding a changed value.
+    pub variables: Vec<String>,
+}
+
+impl super::ConfigAnalyzer {
+    /// Follows the changed values through every task reading them, transitively.
+    pub(crate) fn impact(&self, query: ImpactQuery) -> Impact {
+        // a key of None stands for all keys of the store
+        let mut queue: VecDeque<(Store, Option<String>, usize)> = VecDeque::new();
+        let mut distances: BTreeMap<ProjectKey, usize> = BTreeMap::new();
+        match &query {
+            ImpactQuery::Project { project } => {
+                queue.push_back((
+                    Store::new(StoreKind::TerraformState, &project.to_string()),
+                    None,
+                    1,
+                ));
+                for file in self.project_configs.get(project).into_iter().flatten() {
+                    for task in &file.config.tasks {
+                        let Some(store) = target_store(project, &task.target) else {
+                            continue;
+                        };
+                        for target in task.mapping.values().flatten() {
+                            queue.push_back((store.clone(), Some(target.key().to_owned()), 1));
+                        }
+                    }
+                }
+                // dependencies found by matching stores, e.g. Redis hosts sharing keys
+                let mut dependencies = VecDeque::from([(project, 0)]);
+                while let Some((from, distance)) = dependencies.pop_front() {
+                    for dependency in self.project_dependencies.iter().filter(|d| &d.from == from) {
+                        if !distances.contains_key(&dependency.to) && &dependency.to != project {
+                            distances.insert(dependency.to.clone(), distance + 1);
+                            dependencies.push_back((&dependency.to, distance + 1));
+                        }
+                    }
+                }
+            }
+            ImpactQuery::Store { store } => queue.push_back((store.clone(), None, 1)),
+            ImpactQuery::Key { store, key } => {
+                queue.push_back((store.clone(), Some(key.clone()), 1))
+            }
+        }
+
+        let mut visited = BTreeSet::new();
+        let mut tasks: BTreeMap<ProjectKey, Vec<AffectedTask>> = BTreeMap::new();
+        while let Some((store, key, distance)) = queue.pop_front() {
+            if !visited.insert((store.clone(), key.clone())) {
+                continue;
+            }
+            for (owner, files) in &self.project_configs {
+                for file in files {
+                    for task in &file.config.tasks {
+                        if source_store(owner, &task.source).as_ref() != Some(&store) {
+                            continue;
+                        }
+                        let mut keys = Vec::new();
+                        let mut targets: Vec<&str> = Vec::new();
+                        for (source, mapped) in &task.mapping {
+                            if key
+                                .as_ref()
+                                .is_none_or(|key| store_key_matches(store.kind, source, key))
+                            {
+                                keys.push(source.clone());
+                                targets.extend(mapped.iter().map(|t| t.key()));
+                            }
+                        }
+                        keys.sort();
+                        if targets.is_empty() {
+                            continue;
+                        }
+                        let known = distances.entry(owner.clone()).or_insert(distance);
+                        *known = (*known).min(distance);
+                        let affected = AffectedTask {
+                            file: file.path.clone(),
+                            source: task.source.to_string(),
+                            target: task.target.to_string(),
+                            store: store.clone(),
+                            keys,
+                            variables: targets.iter().map(|t| t.to_string()).collect(),
+                        };
+                        let project_tasks = tasks.entry(owner.clone()).or_default();
+                        if !project_tasks.contains(&affected) {
+                            project_tasks.push(affected);
+                        }
+                        if let Some(next) = target_store(owner, &task.target) {
+                            for target in targets {
+                                queue.push_back((
+                                    next.clone(),
+                                    Some(target.to_owned()),
+                                    distance + 1,
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut projects: Vec<AffectedProject> = distances
+            .into_iter()
+            .map(|(project, distance)| AffectedProject {
+                name: self.project_name(&project),
+                tasks: tasks.remove(&project).unwrap_or_default(),
+                distance,
+                project,
+            })
+            .collect();
+        projects.sort_by(|a, b| (a.distance, &a.project).cmp(&(b.distance, &b.project)));
+        Impact { query, projects }
+    }
+
+    /// Actions for the selected nodes, shown on right click.
+    pub(crate) fn node_menu_ui(&mut self, ui: &mut egui::Ui) {
+        let queries: Vec<ImpactQuery> = self
+            .selected_nodes()
+            .into_iter()
+            .filter_map(|key| match key {
+                NodeKey::Project(project) => Some(ImpactQuery::Project { project }),
+                NodeKey::Store(store) => Some(ImpactQuery::Store { store }),
+                NodeKey::Group(_) => None,
+            })
+            .collect();
+        if queries.is_empty() {
+            ui.label("Select a project or store");
+        }
+        for query in queries {
+            let label = match &query {
+                ImpactQuery::Project { project } => format!("Impact of project {project}"),
+                ImpactQuery::Store { store } => format!("Impact of {store}"),
+                ImpactQuery::Key { store, key } => format!("Impact of {key} in {store}"),
+            };
+            if ui.button(label).clicked() {
+                self.impact = Some(self.impact(query));
+                ui.close_menu();
+            }
+        }
+    }
+
+    pub(crate) fn impact_ui(&mut self, ctx: &egui::Context) {
+        let Some(impact) = &self.impact else {
+            return;
+        };
+        let mut open = true;
+        let mut narrow = None;
+        egui::Window::new("Impact")
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.label(match &impact.query {
+                    ImpactQuery::Project { project } => format!("Change of project {project}"),
+                    ImpactQuery::Store { store } => format!("Change of {store}"),
+                    ImpactQuery::Key { store, key } => format!("Change of {key} in {store}"),
+                });
+                if impact.projects.is_empty() {
+                    ui.label("Nothing is affected.");
+                }
+                for project in &impact.projects {
+                    ui.collapsing(
+                        format!("{} ({} away)", project.name, project.distance),
+                        |ui| {
+                            for task in &project.tasks {
+                                ui.label(format!(
+                                    "{}: {} → {}",
+                                    task.file, task.source, task.target
+                                ));
+                                for variable in &task.variables {
+                                    ui.label(variable);
+                                }
+                                // only tasks reading the queried store know its keys
+                                let ImpactQuery::Store { store } = &impact.query else {
+                                    continue;
+                                };
+                                if task.store != *store {
+                                    continue;
+                                }
+                                for key in &task.keys {
+                                    ui.horizontal(|ui| {
+                                        ui.label(format!("Key {key}"));
+                                        if ui.small_button("Only this key").clicked() {
+                                            narrow = Some(ImpactQuery::Key {
+                                                store: store.clone(),
+                                                key: key.clone(),
+                                            });
+                                        }
+                                    });
+                                }
+                            }
+                        },
+                    );
+                }
+            });
+        if let Some(query) = narrow {
+            self.impact = Some(self.impact(query));
+        } else if !open {
+            self.impact = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ImpactQuery;
+    use crate::{
+        graph::{Store, StoreKind},
+        loader::test_config_file,
+        ConfigAnalyzer, ProjectKey,
+    };
+
+    #[test]
+    pub fn impact_follows_keys_through_stores() {
+        let key = |id| ProjectKey::new("gitlab.com", id);
+        let mut app = ConfigAnalyzer::default();
+        // 1 reads db-password from the vault and passes it on to redis as DB, 2 reads DB from redis
+        app.project_configs.insert(
+            key(1),
+            vec![test_config_file(
+                "cli-config-test.json",
+                r#"[{"source": {"type": "AzureKeyvault", "url": "https://kv.vault.azure.net", "secretType": "secret"},
+                    "target": {"type": "Redis", "hostname": "cache"},
+                    "mapping": {"db-password": ["DB"], "other": ["OTHER"]}}]"#,
+            )],
+        );
+        app.project_configs.insert(
+            key(2),
+            vec![test_config_file(
+                "cli-config-test.json",
+                r#"[{"source": {"type": "Redis", "hostname": "cache"},
+                    "target": {"type": "ProcessEnvironment"},
+                    "mapping": {"DB": ["DB_PASSWORD"]}}]"#,
+            )],
+        );
+        let vault = Store::new(StoreKind::KeyVault, "https://kv.vault.azure.net");
+
+        let impact = app.impact(ImpactQuery::Key {
+            store: vault.clone(),
+            key: "db-password".to_owned(),
+        });
+        let affected: Vec<_> = impact
+            .projects
+            .iter()
+            .map(|p| (p.project.id, p.distance, p.tasks[0].variables.clone()))
+            .collect();
+        assert_eq!(
+            affected,
+            vec![
+                (1, 1, vec!["DB".to_owned()]),
+                (2, 2, vec!["DB_PASSWORD".to_owned()])
+            ]
+        );
+
+        let impact = app.impact(ImpactQuery::Key {
+            store: vault.clone(),
+            key: "other".to_owned(),
+        });
+        assert_eq!(impact.projects.len(), 1);
+
+        // the keys of the queried store narrow a store query, not the target variables
+        let impact = app.impact(ImpactQuery::Store {
+            store: vault.clone(),
+        });
+        let direct = &impact.projects[0].tasks[0];
+        assert_eq!(direct.store, vault);
+        assert_eq!(direct.keys, vec!["db-password", "other"]);
+        let downstream = &impact.projects[1].tasks[0];
+        assert_eq!(downstream.store, Store::new(StoreKind::Redis, "cache"));
+        assert_eq!(downstream.keys, vec!["DB"]);
+        let narrowed = app.impact(ImpactQuery::Key {
+            store: vault,
+            key: direct.keys[0].clone(),
+        });
+        assert_eq!(narrowed.projects.len(), 2);
+    }
+}
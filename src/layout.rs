@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, HashMap};
+
+use eframe::epaint::Vec2;
+use petgraph::{
+    algo::tarjan_scc,
+    stable_graph::{NodeIndex, StableGraph},
+    Direction, EdgeType,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{ProjectNode, DEFAULT_SPAWN_SIZE};
+
+/// Distance between neighbouring nodes in the layered and circular layouts.
+pub const NODE_SPACING: f32 = 50.;
+const FORCE_ITERATIONS: usize = 200;
+
+/// Nodes which can be grouped into clusters by a layout.
+pub trait Clustered {
+    fn cluster(&self) -> &str;
+}
+
+impl Clustered for ProjectNode {
+    fn cluster(&self) -> &str {
+        &self.group
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LayoutKind {
+    /// Layers following the dependency direction (Sugiyama).
+    #[default]
+    Layered,
+    /// Fruchterman-Reingold starting from seeded positions.
+    ForceDirected,
+    /// A circle with the nodes of a cluster next to each other.
+    Circular,
+}
+
+impl LayoutKind {
+    pub const ALL: [LayoutKind; 3] = [
+        LayoutKind::Layered,
+        LayoutKind::ForceDirected,
+        LayoutKind::Circular,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LayoutKind::Layered => "Layered",
+            LayoutKind::ForceDirected => "Force directed",
+            LayoutKind::Circular => "Circular",
+        }
+    }
+}
+
+/// Computes node locations for `g`. The result only depends on the graph, `kind` and `seed`.
+pub fn compute<N: Clustered + Ord, E, Ty: EdgeType>(
+    g: &StableGraph<N, E, Ty>,
+    kind: LayoutKind,
+    seed: u64,
+) -> HashMap<NodeIndex, Vec2> {
+    match kind {
+        LayoutKind::Layered => layered(g),
+        LayoutKind::ForceDirected => force_directed(g, seed),
+        LayoutKind::Circular => circular(g),
+    }
+}
+
+/// Node indices ordered by cluster and node data, the starting point of every layout.
+fn sorted_nodes<N: Clustered + Ord, E, Ty: EdgeType>(g: &StableGraph<N, E, Ty>) -> Vec<NodeIndex> {
+    let mut nodes: Vec<NodeIndex> = g.node_indices().collect();
+    nodes.sort_by(|a, b| {
+        let (a, b) = (&g[*a], &g[*b]);
+        a.cluster().cmp(b.cluster()).then_with(|| a.cmp(b))
+    });
+    nodes
+}
+
+fn layered<N: Clustered + Ord, E, Ty: EdgeType>(
+    g: &StableGraph<N, E, Ty>,
+) -> HashMap<NodeIndex, Vec2> {
+    // rank the condensation, so all projects of a dependency cycle share a layer
+    let components = tarjan_scc(g);
+    let mut component: HashMap<NodeIndex, usize> = HashMap::new();
+    for (index, nodes) in components.iter().enumerate() {
+        for node in nodes {
+            component.insert(*node, index);
+        }
+    }
+    // tarjan_scc returns the components in reverse topological order
+    let mut rank = vec![0usize; components.len()];
+    for index in (0..components.len()).rev() {
+        for node in &components[index] {
+            for next in g.neighbors_directed(*node, Direction::Outgoing) {
+                let next = component[&next];
+                if next != index {
+                    rank[next] = rank[next].max(rank[index] + 1);
+                }
+            }
+        }
+    }
+
+    let mut layers: BTreeMap<usize, Vec<NodeIndex>> = BTreeMap::new();
+    for node in sorted_nodes(g) {
+        layers.entry(rank[component[&node]]).or_default().push(node);
+    }
+    let mut layers: Vec<Vec<NodeIndex>> = layers.into_values().collect();
+
+    // reduce crossings with a few barycenter sweeps, down and up again
+    let mut position: HashMap<NodeIndex, f32> = HashMap::new();
+    let update_positions = |layers: &[Vec<NodeIndex>], position: &mut HashMap<NodeIndex, f32>| {
+        for layer in layers {
+            for (x, node) in layer.iter().enumerate() {
+                position.insert(*node, x as f32);
+            }
+        }
+    };
+    update_positions(&layers, &mut position);
+    for _ in 0..4 {
+        for (range, direction) in [
+            ((1..layers.len()).collect::<Vec<_>>(), Direction::Incoming),
+            (
+                (0..layers.len().saturating_sub(1)).rev().collect(),
+                Direction::Outgoing,
+            ),
+        ] {
+            for index in range {
+                let barycenter = |node: &NodeIndex| {
+                    let neighbours: Vec<f32> = g
+                        .neighbors_directed(*node, direction)
+                        .filter(|n| n != node)
+                        .map(|n| position[&n])
+                        .collect();
+                    if neighbours.is_empty() {
+                        position[node]
+                    } else {
+                        neighbours.iter().sum::<f32>() / neighbours.len() as f32
+                    }
+                };
+                let mut keyed: Vec<(f32, f32, NodeIndex)> = layers[index]
+                    .iter()
+                    .map(|node| (barycenter(node), position[node], *node))
+                    .collect();
+                keyed.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+                layers[index] = keyed.into_iter().map(|(_, _, node)| node).collect();
+                update_positions(&layers[index..=index], &mut position);
+            }
+        }
+    }
+
+    let mut locations = HashMap::new();
+    for (y, layer) in layers.iter().enumerate() {
+        let offset = (layer.len() as f32 - 1.) / 2.;
+        for (x, node) in layer.iter().enumerate() {
+            locations.insert(
+                *node,
+                Vec2::new(
+                    (x as f32 - offset) * NODE_SPACING,
+                    y as f32 * NODE_SPACING * 1.5,
+                ),
+            );
+        }
+    }
+    locations
+}
+
+fn force_directed<N: Clustered + Ord, E, Ty: EdgeType>(
+    g: &StableGraph<N, E, Ty>,
+    seed: u64,
+) -> HashMap<NodeIndex, Vec2> {
+    let nodes = sorted_nodes(g);
+    if nodes.is_empty() {
+        return HashMap::new();
+    }
+    let slot: HashMap<NodeIndex, usize> = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+    let size = DEFAULT_SPAWN_SIZE.max(NODE_SPACING * (nodes.len() as f32).sqrt() * 2.);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut locations: Vec<Vec2> = nodes
+        .iter()
+        .map(|_| Vec2::new(rng.gen_range(0. ..size), rng.gen_range(0. ..size)))
+        .collect();
+    let edges: Vec<(usize, usize)> = g
+        .edge_indices()
+        .filter_map(|e| g.edge_endpoints(e))
+        .map(|(a, b)| (slot[&a], slot[&b]))
+        .filter(|(a, b)| a != b)
+        .collect();
+
+    let k = (size * size / nodes.len() as f32).sqrt();
+    for iteration in 0..FORCE_ITERATIONS {
+        let temperature = size / 10. * (1. - iteration as f32 / FORCE_ITERATIONS as f32);
+        let mut displacement = vec![Vec2::ZERO; locations.len()];
+        for i in 0..locations.len() {
+            for j in (i + 1)..locations.len() {
+                let delta = locations[i] - locations[j];
+                let distance = delta.length().max(0.01);
+                let force = delta / distance * (k * k / distance);
+                displacement[i] += force;
+                displacement[j] -= force;
+            }
+        }
+        for (a, b) in &edges {
+            let delta = locations[*a] - locations[*b];
+            let distance = delta.length().max(0.01);
+            let force = delta / distance * (distance * distance / k);
+            displacement[*a] -= force;
+            displacement[*b] += force;
+        }
+        for (location, displacement) in locations.iter_mut().zip(displacement) {
+            let length = displacement.length();
+            if length > 0. {
+                *location += displacement / length * length.min(temperature);
+            }
+        }
+    }
+    nodes.into_iter().zip(locations).collect()
+}
+
+fn circular<N: Clustered + Ord, E, Ty: EdgeType>(
+    g: &StableGraph<N, E, Ty>,
+) -> HashMap<NodeIndex, Vec2> {
+    let nodes = sorted_nodes(g);
+    // one empty slot between clusters
+    let mut slots: Vec<Option<NodeIndex>> = Vec::new();
+    for node in nodes {
+        if let Some(previous) = slots.last().copied().flatten() {
+            if g[previous].cluster() != g[node].cluster() {
+                slots.push(None);
+            }
+        }
+        slots.push(Some(node));
+    }
+    let radius = (slots.len() as f32 * NODE_SPACING / std::f32::consts::TAU).max(NODE_SPACING);
+    let step = std::f32::consts::TAU / slots.len().max(1) as f32;
+    slots
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, node)| {
+            let angle = i as f32 * step;
+            node.map(|node| (node, Vec2::new(angle.cos(), angle.sin()) * radius))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::stable_graph::StableGraph;
+
+    use super::{compute, Clustered, LayoutKind};
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct TestNode(&'static str, &'static str);
+
+    impl Clustered for TestNode {
+        fn cluster(&self) -> &str {
+            self.0
+        }
+    }
+
+    #[test]
+    pub fn layered_follows_dependencies_and_keeps_cycles_together() {
+        let mut g: StableGraph<TestNode, ()> = StableGraph::new();
+        let a = g.add_node(TestNode("infra", "a"));
+        let b = g.add_node(TestNode("infra", "b"));
+        let c = g.add_node(TestNode("apps", "c"));
+        let d = g.add_node(TestNode("apps", "d"));
+        g.add_edge(a, b, ());
+        g.add_edge(b, c, ());
+        g.add_edge(c, d, ());
+        g.add_edge(d, c, ());
+
+        let locations = compute(&g, LayoutKind::Layered, 0);
+        assert!(locations[&a].y < locations[&b].y);
+        assert!(locations[&b].y < locations[&c].y);
+        assert_eq!(locations[&c].y, locations[&d].y);
+    }
+
+    #[test]
+    pub fn layouts_are_deterministic() {
+        let mut g: StableGraph<TestNode, ()> = StableGraph::new();
+        let nodes: Vec<_> = ["a", "b", "c", "d", "e"]
+            .into_iter()
+            .map(|name| g.add_node(TestNode("group", name)))
+            .collect();
+        for pair in nodes.windows(2) {
+            g.add_edge(pair[0], pair[1], ());
+        }
+        for kind in LayoutKind::ALL {
+            assert_eq!(compute(&g, kind, 7), compute(&g, kind, 7), "{kind:?}");
+        }
+        assert_ne!(
+            compute(&g, LayoutKind::ForceDirected, 7),
+            compute(&g, LayoutKind::ForceDirected, 8)
+        );
+    }
+}
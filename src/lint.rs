@@ -0,0 +1,365 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use petgraph::{algo::tarjan_scc, graphmap::DiGraphMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configuration_schema::SourceConfig,
+    graph::{resolve_project, source_store, target_store, Store},
+    loader::ConfigFile,
+    matching::store_key_matches,
+    terraform::{state_key, state_name},
+    ProjectKey,
+};
+
+/// A variable read from a store no analyzed project writes it to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnresolvedVariable {
+    pub project: ProjectKey,
+    pub file: String,
+    pub store: Store,
+    pub key: String,
+}
+
+impl Display for UnresolvedVariable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} from {} in {} ({})",
+            self.key, self.store, self.project, self.file
+        )
+    }
+}
+
+/// A configuration file the tool would reject or misread.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ConfigProblem {
+    pub project: ProjectKey,
+    pub file: String,
+    pub message: String,
+}
+
+impl Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in {} ({})", self.message, self.project, self.file)
+    }
+}
+
+/// The configuration problems of `file`: why it is not a configuration, which placeholders
+/// could not be expanded or what is invalid in it.
+pub fn config_problems(project: &ProjectKey, file: &ConfigFile) -> Vec<ConfigProblem> {
+    file.load_error
+        .iter()
+        .chain(&file.template_errors)
+        .cloned()
+        .chain(file.config.validate())
+        .map(|message| ConfigProblem {
+            project: project.clone(),
+            file: file.path.clone(),
+            message,
+        })
+        .collect()
+}
+
+/// A mapped Terraform output which does not exist.
+///
+/// Sensitive outputs written where they are not kept secret are sensitive flows.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TerraformOutputProblem {
+    pub project: ProjectKey,
+    pub file: String,
+    pub output: String,
+    pub kind: TerraformOutputProblemKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TerraformOutputProblemKind {
+    /// The output is not in the Terraform file or state `source`.
+    Missing { source: String },
+}
+
+impl Display for TerraformOutputProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            TerraformOutputProblemKind::Missing { source } => write!(
+                f,
+                "output {} missing from {source} in {} ({})",
+                self.output, self.project, self.file
+            ),
+        }
+    }
+}
+
+/// Problems of the Terraform outputs the tasks of `file` map, where the outputs are known.
+pub fn terraform_output_problems(
+    project: &ProjectKey,
+    file: &ConfigFile,
+) -> Vec<TerraformOutputProblem> {
+    let mut problems = Vec::new();
+    for task in &file.config.tasks {
+        let (source, outputs) = match &task.source {
+            SourceConfig::TerraformFile(terraform) => {
+                let name = terraform.file_name.to_string_lossy().into_owned();
+                let outputs = file.terraform_outputs.get(&name);
+                (name, outputs)
+            }
+            SourceConfig::GitlabProjectTerraformState(config) => {
+                let (state_project, name) = (resolve_project(project, config), state_name(config));
+                let outputs = file.terraform_states.get(&state_key(&state_project, name));
+                (
+                    format!("terraform state {name} of {state_project}"),
+                    outputs,
+                )
+            }
+            _ => continue,
+        };
+        let Some(outputs) = outputs else {
+            continue;
+        };
+        for output in task.mapping.keys().filter(|o| !outputs.contains_key(*o)) {
+            problems.push(TerraformOutputProblem {
+                project: project.clone(),
+                file: file.path.clone(),
+                output: output.clone(),
+                kind: TerraformOutputProblemKind::Missing {
+                    source: source.clone(),
+                },
+            });
+        }
+    }
+    problems
+}
+
+impl super::ConfigAnalyzer {
+    /// Invalid configuration files and invalid tasks of all projects.
+    pub(crate) fn config_problems(&self) -> BTreeSet<ConfigProblem> {
+        self.project_configs
+            .iter()
+            .flat_map(|(project, files)| {
+                files
+                    .iter()
+                    .flat_map(move |file| config_problems(project, file))
+            })
+            .collect()
+    }
+
+    /// Problems of the mapped Terraform outputs of all projects.
+    pub(crate) fn terraform_output_problems(&self) -> BTreeSet<TerraformOutputProblem> {
+        self.project_configs
+            .iter()
+            .flat_map(|(project, files)| {
+                files
+                    .iter()
+                    .flat_map(move |file| terraform_output_problems(project, file))
+            })
+            .collect()
+    }
+
+    /// Variables read from stores which no analyzed project writes them to.
+    ///
+    /// Stores without any writer in the analysis are managed elsewhere and not checked.
+    pub(crate) fn unresolved_variables(&self) -> BTreeSet<UnresolvedVariable> {
+        let mut written: BTreeMap<Store, BTreeSet<&str>> = BTreeMap::new();
+        for (owner, files) in &self.project_configs {
+            for file in files {
+                for task in &file.config.tasks {
+                    if let Some(store) = target_store(owner, &task.target) {
+                        written
+                            .entry(store)
+                            .or_default()
+                            .extend(task.mapping.values().flatten().map(|t| t.key()));
+                    }
+                }
+            }
+        }
+
+        let mut unresolved = BTreeSet::new();
+        for (owner, files) in &self.project_configs {
+            for file in files {
+                for task in &file.config.tasks {
+                    let Some(store) = source_store(owner, &task.source) else {
+                        continue;
+                    };
+                    let Some(keys) = written.get(&store) else {
+                        continue;
+                    };
+                    for key in task.mapping.keys().filter(|key| {
+                        !keys
+                            .iter()
+                            .any(|stored| store_key_matches(store.kind, key, stored))
+                    }) {
+                        unresolved.insert(UnresolvedVariable {
+                            project: owner.clone(),
+                            file: file.path.clone(),
+                            store: store.clone(),
+                            key: key.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        unresolved
+    }
+
+    /// Groups of projects depending on each other in a cycle, each sorted.
+    pub(crate) fn dependency_cycles(&self) -> Vec<Vec<ProjectKey>> {
+        let graph: DiGraphMap<&ProjectKey, ()> = self
+            .project_dependencies
+            .iter()
+            .map(|d| (&d.from, &d.to))
+            .collect();
+        let mut cycles: Vec<Vec<ProjectKey>> = tarjan_scc(&graph)
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .map(|component| {
+                let mut cycle: Vec<ProjectKey> = component.into_iter().cloned().collect();
+                cycle.sort();
+                cycle
+            })
+            .collect();
+        cycles.sort();
+        cycles
+    }
+}
+
+/// A cycle written as `a → b → a`.
+pub fn cycle_name(cycle: &[ProjectKey]) -> String {
+    cycle
+        .iter()
+        .chain(cycle.first())
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" → ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cycle_name;
+    use crate::{
+        configuration_schema::TerraformInputFileFormat,
+        graph::{add_dependency, Reason, Store, StoreKind},
+        loader::test_config_file,
+        terraform::parse_outputs,
+        ConfigAnalyzer, ProjectKey,
+    };
+
+    #[test]
+    pub fn unresolved_variables_of_written_stores() {
+        let mut app = ConfigAnalyzer::default();
+        app.project_configs.insert(
+            ProjectKey::new("gitlab.com", 1),
+            vec![test_config_file(
+                "cli-config-writer.json",
+                r#"[{"source": {"type": "Environment"},
+                    "target": {"type": "Redis", "hostname": "cache"},
+                    "mapping": {"URL": ["url"]}}]"#,
+            )],
+        );
+        app.project_configs.insert(
+            ProjectKey::new("gitlab.com", 2),
+            vec![test_config_file(
+                "cli-config-reader.json",
+                r#"[{"source": {"type": "Redis", "hostname": "cache"},
+                    "target": {"type": "ProcessEnvironment"},
+                    "mapping": {"url": ["URL"], "password": ["PASSWORD"]}},
+                   {"source": {"type": "Redis", "hostname": "external"},
+                    "target": {"type": "ProcessEnvironment"},
+                    "mapping": {"anything": ["ANYTHING"]}}]"#,
+            )],
+        );
+
+        let unresolved: Vec<_> = app
+            .unresolved_variables()
+            .into_iter()
+            .map(|u| u.to_string())
+            .collect();
+        assert_eq!(
+            unresolved,
+            vec!["password from Redis cache in gitlab.com#2 (cli-config-reader.json)"]
+        );
+    }
+
+    #[test]
+    pub fn cycles_of_dependencies() {
+        let key = |id| ProjectKey::new("gitlab.com", id);
+        let mut app = ConfigAnalyzer::default();
+        for (from, to) in [(1, 2), (2, 3), (3, 1), (3, 4)] {
+            add_dependency(
+                &mut app.project_dependencies,
+                &key(from),
+                &key(to),
+                Reason::new(Store::new(StoreKind::Redis, "cache"), Vec::new()),
+            );
+        }
+
+        let cycles = app.dependency_cycles();
+        assert_eq!(cycles, vec![vec![key(1), key(2), key(3)]]);
+        assert_eq!(
+            cycle_name(&cycles[0]),
+            "gitlab.com#1 → gitlab.com#2 → gitlab.com#3 → gitlab.com#1"
+        );
+    }
+
+    #[test]
+    pub fn missing_and_leaked_terraform_outputs() {
+        let mut file = test_config_file(
+            "cli-config-infra.json",
+            r#"[{"source": {"type": "TerraformFile", "file_name": "tfoutput.json", "file_format": "OutputJson"},
+                "target": {"type": "EnvFile", "file": ".env"},
+                "mapping": {"db_host": ["DB_HOST"], "db_password": ["DB_PASSWORD"], "db_user": ["DB_USER"]}},
+               {"source": {"type": "TerraformFile", "file_name": "tfoutput.json", "file_format": "OutputJson"},
+                "target": {"type": "GitlabProjectVariables", "config": {"project_id": 2},
+                           "details": {"masked_variables": ["DB_PASSWORD"]}},
+                "mapping": {"db_password": ["DB_PASSWORD"]}},
+               {"source": {"type": "GitlabProjectTerraformState", "project_id": 42, "environment": "production"},
+                "target": {"type": "ProcessEnvironment"},
+                "mapping": {"cluster_name": ["CLUSTER"]}}]"#,
+        );
+        file.terraform_outputs.insert(
+            "tfoutput.json".to_owned(),
+            parse_outputs(
+                r#"{"db_host": {"sensitive": false, "type": "string", "value": "db"},
+                    "db_password": {"sensitive": true, "type": "string", "value": "x"}}"#,
+                &TerraformInputFileFormat::OutputJson,
+            )
+            .unwrap(),
+        );
+        file.terraform_states.insert(
+            "gitlab.com#42 production".to_owned(),
+            parse_outputs(
+                r#"{"version": 4, "outputs": {}}"#,
+                &TerraformInputFileFormat::State,
+            )
+            .unwrap(),
+        );
+        let mut app = ConfigAnalyzer::default();
+        app.project_configs
+            .insert(ProjectKey::new("gitlab.com", 1), vec![file]);
+
+        let problems: Vec<_> = app
+            .terraform_output_problems()
+            .into_iter()
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                "output cluster_name missing from terraform state production of gitlab.com#42 in gitlab.com#1 (cli-config-infra.json)",
+                "output db_user missing from tfoutput.json in gitlab.com#1 (cli-config-infra.json)"
+            ]
+        );
+        // the sensitive output is only reported once, where it is exposed
+        let flows: Vec<_> = app
+            .sensitive_flows()
+            .into_iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert_eq!(
+            flows,
+            vec!["db_password from sensitive output db_password of tfoutput.json written to EnvFile .env DB_PASSWORD in gitlab.com#1 (cli-config-infra.json)"]
+        );
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::BTreeMap;
+
+use gitlab::{
+    api::{
+        paged,
+        projects::repository::{files::FileBuilder, TreeBuilder},
+        ApiError, Pagination, Query,
+    },
+    types::RepoTreeObject,
+    RestError,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configuration_schema::{SourceConfig, Task, VariableShareConfig},
+    gitlab_file::File,
+    gitlab_group::discover_projects,
+    graph::resolve_project,
+    secrets::{scan_content, SecretMatch},
+    template::expand_config,
+    terraform::{fetch_state_outputs, parse_outputs, state_key, state_name, TerraformOutputs},
+    ProjectKey,
+};
+
+/// The ref configuration files are read from.
+pub const CONFIG_REF: &str = "main";
+
+/// A configuration file read from a project repository.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConfigFile {
+    pub path: String,
+    #[serde(rename = "ref")]
+    pub ref_: String,
+    pub config: VariableShareConfig,
+    /// Outputs of the Terraform files the tasks read, by file name, if committed.
+    #[serde(default)]
+    pub terraform_outputs: BTreeMap<String, TerraformOutputs>,
+    /// Outputs of the GitLab-managed states the tasks read, by [state_key].
+    #[serde(default)]
+    pub terraform_states: BTreeMap<String, TerraformOutputs>,
+    /// The environment the placeholders of the file were expanded for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    /// Placeholders which could not be expanded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub template_errors: Vec<String>,
+    /// The tasks before their placeholders were expanded, empty without parameters.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub template: Vec<Task>,
+    /// Why the content is not a valid configuration, the file has no tasks then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load_error: Option<String>,
+    /// Values of the file content which look like secrets.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<SecretMatch>,
+}
+
+impl ConfigFile {
+    /// The task at `index` as written in the file, before its placeholders were expanded.
+    pub fn template_task(&self, index: usize) -> &Task {
+        self.template
+            .get(index)
+            .unwrap_or(&self.config.tasks[index])
+    }
+}
+
+/// All tasks of the configuration files of a project.
+pub fn tasks(files: &[ConfigFile]) -> impl Iterator<Item = &Task> {
+    files.iter().flat_map(|file| file.config.tasks.iter())
+}
+
+/// Whether a repository file is a configuration file of the analyzed tool.
+pub fn is_config_file(name: &str) -> bool {
+    name.starts_with("cli-config-") || name.starts_with("redis") && name.ends_with(".json")
+    // TODO yaml
+}
+
+/// Lists the configuration files in the repository of `project` at `ref_`.
+pub fn config_files(
+    client: &gitlab::Gitlab,
+    project: u64,
+    ref_: &str,
+) -> Result<Vec<RepoTreeObject>, ApiError<RestError>> {
+    // https://docs.gitlab.com/ee/api/repositories.html#list-repository-tree
+    let endpoint = TreeBuilder::default()
+        .ref_(ref_)
+        .project(project)
+        .recursive(true)
+        .build()
+        .unwrap();
+    let tree: Vec<RepoTreeObject> = paged(endpoint, Pagination::All).query(client)?;
+    Ok(tree
+        .into_iter()
+        .filter(|o| is_config_file(&o.name))
+        .collect())
+}
+
+/// Reads the content of a repository file.
+pub fn load_file_content(
+    client: &gitlab::Gitlab,
+    project: u64,
+    path: &str,
+    ref_: &str,
+) -> Result<String, String> {
+    let file: File = FileBuilder::default()
+        .project(project)
+        .file_path(path)
+        .ref_(ref_)
+        .build()
+        .unwrap()
+        .query(client)
+        .map_err(|e| format!("Failed to load file content for {path}: {e}"))?;
+    file.get_content()
+        .map_err(|e| format!("Failed to load file content for {path}: {e}"))
+}
+
+/// Parses the content of a configuration file.
+pub fn parse_config(path: &str, content: &str) -> Result<VariableShareConfig, String> {
+    serde_json::from_str(content)
+        .map_err(|e| format!("Content of {path} is not a valid configuration: {e}"))
+}
+
+/// The repository path of `name` written in the configuration file at `config_path`.
+///
+/// Relative names are resolved against the directory of the configuration file.
+pub fn repository_path(config_path: &str, name: &str) -> String {
+    let mut parts: Vec<&str> = match name.strip_prefix('/') {
+        Some(_) => Vec::new(),
+        None => config_path.split('/').collect(),
+    };
+    // drop the file name of the configuration
+    parts.pop();
+    for part in name.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Reads the outputs of the Terraform files `config` reads which are committed to the repository.
+///
+/// Files usually written by the pipeline before the tool runs are missing, their outputs
+/// are not checked.
+pub fn load_terraform_outputs(
+    client: &gitlab::Gitlab,
+    project: u64,
+    path: &str,
+    config: &VariableShareConfig,
+    ref_: &str,
+) -> BTreeMap<String, TerraformOutputs> {
+    let mut outputs = BTreeMap::new();
+    for task in &config.tasks {
+        let SourceConfig::TerraformFile(terraform) = &task.source else {
+            continue;
+        };
+        let name = terraform.file_name.to_string_lossy().into_owned();
+        if outputs.contains_key(&name) {
+            continue;
+        }
+        let output_path = repository_path(path, &name);
+        let content = match load_file_content(client, project, &output_path, ref_) {
+            Ok(content) => content,
+            Err(e) => {
+                log::warn!("Outputs of {output_path} read by {path} are not checked: {e}");
+                continue;
+            }
+        };
+        match parse_outputs(&content, &terraform.file_format) {
+            Ok(parsed) => {
+                outputs.insert(name, parsed);
+            }
+            Err(e) => log::error!("{output_path}: {e}"),
+        }
+    }
+    outputs
+}
+
+/// Reads and parses the configuration files of `project` at `ref_`.
+///
+/// Files which can't be fetched fail the whole project, files which are not valid
+/// configurations are kept with their [ConfigFile::load_error].
+pub fn project_config_files(
+    client: &gitlab::Gitlab,
+    project: u64,
+    name: &str,
+    ref_: &str,
+) -> Result<Vec<ConfigFile>, String> {
+    let files = config_files(client, project, ref_)
+        .map_err(|e| format!("Failed to list the files of {name} at {ref_}: {e}"))?;
+    let mut configs = Vec::new();
+    for file in files {
+        log::info!("Json File: {:?} in project {name}", &file.path);
+        let content = load_file_content(client, project, &file.path, ref_)?;
+        let secrets = scan_content(&content);
+        for secret in &secrets {
+            log::warn!("{name} {}: possible secret: {secret}", file.path);
+        }
+        match parse_config(&file.path, &content) {
+            Ok(config) => {
+                log::debug!("Config: {:?}", &config);
+                for expansion in expand_config(&config) {
+                    let config = expansion.config;
+                    for error in &expansion.errors {
+                        log::error!("{name} {}: {error}", file.path);
+                    }
+                    for problem in config.validate() {
+                        log::warn!("{name} {}: {problem}", file.path);
+                    }
+                    configs.push(ConfigFile {
+                        terraform_outputs: load_terraform_outputs(
+                            client, project, &file.path, &config, ref_,
+                        ),
+                        terraform_states: BTreeMap::new(),
+                        path: file.path.clone(),
+                        ref_: ref_.to_owned(),
+                        config,
+                        environment: expansion.environment,
+                        template_errors: expansion.errors,
+                        template: expansion.template,
+                        load_error: None,
+                        secrets: secrets.clone(),
+                    });
+                }
+            }
+            Err(e) => {
+                log::error!("{name}: {e}");
+                configs.push(ConfigFile {
+                    path: file.path,
+                    ref_: ref_.to_owned(),
+                    config: VariableShareConfig {
+                        version: semver::Version::new(0, 0, 0),
+                        tasks: Vec::new(),
+                        secret_allowlist: Vec::new(),
+                        parameters: BTreeMap::new(),
+                    },
+                    terraform_outputs: BTreeMap::new(),
+                    terraform_states: BTreeMap::new(),
+                    environment: None,
+                    template_errors: Vec::new(),
+                    template: Vec::new(),
+                    load_error: Some(e),
+                    secrets,
+                });
+            }
+        }
+    }
+    Ok(configs)
+}
+
+impl super::ConfigAnalyzer {
+    pub(crate) fn load_projects(&mut self) {
+        for instance in self.settings.instances.iter().filter(|i| i.enabled) {
+            let Some(client) = self.clients.get(&instance.host()) else {
+                log::warn!("Instance {} is not connected", instance.name);
+                continue;
+            };
+            // https://docs.gitlab.com/ee/api/groups.html#list-a-groups-projects
+            for group in &instance.groups {
+                match discover_projects(client, group, &self.settings.discovery) {
+                    Ok(projects) => {
+                        log::info!("Group {}: {} projects", group, projects.len());
+                        self.data.extend(projects.into_values().map(|project| {
+                            (
+                                ProjectKey::new(&instance.host(), project.id.value()),
+                                project,
+                            )
+                        }));
+                    }
+                    Err(e) => {
+                        log::error!("Error: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn load_graph_input_data(&mut self) {
+        self.project_configs = self.load_configs_at(CONFIG_REF);
+        for problem in self.terraform_output_problems() {
+            log::warn!("Terraform: {problem}");
+        }
+        self.update_project_dependencies();
+        self.generate_graph();
+    }
+
+    /// Reads the configuration files of all loaded projects at `ref_`.
+    pub(crate) fn load_configs_at(&self, ref_: &str) -> BTreeMap<ProjectKey, Vec<ConfigFile>> {
+        let mut project_configs: BTreeMap<ProjectKey, Vec<ConfigFile>> = BTreeMap::new();
+        for (key, project) in self.data.iter() {
+            let Some(client) = self.clients.get(&*key.host) else {
+                log::warn!("No connection to {} for {}", key.host, project.name);
+                continue;
+            };
+            let mut files = match project_config_files(client, key.id, &project.name, ref_) {
+                Ok(files) => files,
+                Err(e) => {
+                    log::error!("{e}");
+                    continue;
+                }
+            };
+            self.load_terraform_states(key, &mut files);
+            if !files.is_empty() {
+                project_configs.insert(key.clone(), files);
+            }
+        }
+        project_configs
+    }
+
+    /// Downloads the GitLab-managed Terraform states the tasks of `owner` read.
+    ///
+    /// States on instances without a connection or without access are skipped.
+    pub(crate) fn load_terraform_states(&self, owner: &ProjectKey, files: &mut [ConfigFile]) {
+        for file in files {
+            for task in &file.config.tasks {
+                let SourceConfig::GitlabProjectTerraformState(config) = &task.source else {
+                    continue;
+                };
+                let project = resolve_project(owner, config);
+                let name = state_name(config);
+                let key = state_key(&project, name);
+                if file.terraform_states.contains_key(&key) {
+                    continue;
+                }
+                let Some(client) = self.clients.get(&*project.host) else {
+                    log::warn!(
+                        "No connection to {} for terraform state {key}",
+                        project.host
+                    );
+                    continue;
+                };
+                match fetch_state_outputs(client, project.id, name) {
+                    Ok(outputs) => {
+                        file.terraform_states.insert(key, outputs);
+                    }
+                    Err(e) => log::warn!("{e}"),
+                }
+            }
+        }
+    }
+}
+
+/// A configuration file with the given tasks, written as json.
+#[cfg(test)]
+pub fn test_config_file(path: &str, tasks: &str) -> ConfigFile {
+    let content = format!(r#"{{"version": "1.0.0", "tasks": {tasks}}}"#);
+    ConfigFile {
+        path: path.to_owned(),
+        ref_: CONFIG_REF.to_owned(),
+        config: serde_json::from_str(&content).unwrap(),
+        terraform_outputs: BTreeMap::new(),
+        terraform_states: BTreeMap::new(),
+        environment: None,
+        template_errors: Vec::new(),
+        template: Vec::new(),
+        load_error: None,
+        secrets: scan_content(&content),
+    }
+}
+
+/// The configuration files of `content` expanded for the environments it declares.
+#[cfg(test)]
+pub fn test_expanded_files(path: &str, content: &str) -> Vec<ConfigFile> {
+    let config: VariableShareConfig = serde_json::from_str(content).unwrap();
+    expand_config(&config)
+        .into_iter()
+        .map(|expansion| ConfigFile {
+            config: expansion.config,
+            environment: expansion.environment,
+            template_errors: expansion.errors,
+            template: expansion.template,
+            ..test_config_file(path, "[]")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose, Engine};
+    use mockito::Matcher;
+
+    use super::{project_config_files, repository_path};
+    use crate::settings::test_client;
+
+    #[test]
+    pub fn invalid_files_are_kept_and_fetch_errors_fail() {
+        let mut server = mockito::Server::new();
+        let client = test_client(&mut server);
+        server
+            .mock("GET", "/api/v4/projects/1/repository/tree")
+            .match_query(Matcher::Any)
+            .with_body(
+                r#"[{"id": "a", "name": "cli-config-app.json", "type": "blob",
+                     "path": "cli-config-app.json", "mode": "100644"}]"#,
+            )
+            .create();
+        server
+            .mock(
+                "GET",
+                "/api/v4/projects/1/repository/files/cli-config-app.json",
+            )
+            .match_query(Matcher::Any)
+            .with_body(format!(
+                r#"{{"file_name": "cli-config-app.json", "file_path": "cli-config-app.json",
+                     "size": 9, "encoding": "base64", "content": "{}", "content_sha256": "",
+                     "ref": "feature", "blob_id": "a", "commit_id": "b", "last_commit_id": "c",
+                     "execute_filemode": false}}"#,
+                general_purpose::STANDARD.encode(r#"{"tasks": "#)
+            ))
+            .create();
+        server
+            .mock("GET", "/api/v4/projects/2/repository/tree")
+            .match_query(Matcher::Any)
+            .with_status(500)
+            .with_body(r#"{"message": "500 Internal Server Error"}"#)
+            .create();
+
+        let files = project_config_files(&client, 1, "app", "feature").unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].config.tasks.is_empty());
+        assert!(files[0]
+            .load_error
+            .as_ref()
+            .is_some_and(|e| e.starts_with("Content of cli-config-app.json is not a valid")));
+
+        assert!(project_config_files(&client, 2, "broken", "feature").is_err());
+    }
+
+    #[test]
+    pub fn paths_are_relative_to_the_config_file() {
+        assert_eq!(
+            repository_path("cli-config-app.json", "./out.json"),
+            "out.json"
+        );
+        assert_eq!(
+            repository_path("deploy/prod/cli-config-app.json", "out.json"),
+            "deploy/prod/out.json"
+        );
+        assert_eq!(
+            repository_path("deploy/prod/cli-config-app.json", "../tf/./out.json"),
+            "deploy/tf/out.json"
+        );
+        assert_eq!(
+            repository_path("deploy/cli-config-app.json", "/tf/out.json"),
+            "tf/out.json"
+        );
+    }
+}
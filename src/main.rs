@@ -1,27 +1,56 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
 
-use egui_graphs::{Graph, GraphView, SettingsInteraction};
+use clusters::{draw_group_boxes, group_node_draw};
+use egui_graphs::{
+    Graph, GraphView, Metadata, SettingsInteraction, SettingsNavigation, SettingsStyle,
+};
 use petgraph::Directed;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 // hide console window on Windows in release
 use eframe::egui::{self};
-use gitlab::{
-    api::{groups::GroupBuilder, projects::repository::TreeBuilder, ApiError, Query},
-    Project, RestError,
-};
+use gitlab::Project;
 // use egui_graphs::{default_edge_transform, Graph, GraphView, Node, SettingsInteraction, to_graph_custom};
 // use petgraph::{prelude::*, EdgeType};
 
+mod audit;
+mod cli;
+mod clusters;
 mod configuration_schema;
+mod credentials;
+mod details;
+mod diff;
+mod environment;
+mod filter;
 mod gitlab_file;
 mod gitlab_group;
+mod gitlab_merge_request;
+mod gitlab_terraform_state;
+mod gitlab_variable;
+mod gitlab_version;
 mod graph;
-use gitlab_file::File;
+mod impact;
+mod layout;
+mod lint;
+mod loader;
+mod matching;
+mod merge_request;
+mod policy;
+mod report;
+mod secrets;
+mod sensitivity;
+mod session;
+mod settings;
+mod template;
+mod terraform;
 use gitlab_group::Group;
+use graph::{Dependency, Store, ViewOptions};
+use layout::LayoutKind;
+use session::{Session, SESSION_KEY};
+use settings::Settings;
 
-use crate::configuration_schema::VariableShareConfig;
+use crate::loader::ConfigFile;
 pub const DEFAULT_SPAWN_SIZE: f32 = 250.;
 
 fn main() -> Result<(), eframe::Error> {
@@ -29,6 +58,14 @@ fn main() -> Result<(), eframe::Error> {
         std::env::set_var("RUST_LOG", "info");
     }
     env_logger::init();
+    let cli = <cli::Cli as clap::Parser>::parse();
+    if let Some(command) = cli.command {
+        if let Err(e) = cli::run(command) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     let options = eframe::NativeOptions {
         initial_window_size: Some(egui::vec2(1980.0, 1024.0)),
         ..Default::default()
@@ -36,185 +73,289 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Config Analyzer",
         options,
-        Box::new(|_cc: &eframe::CreationContext<'_>| Box::<ConfigAnalyzer>::default()),
+        Box::new(|cc: &eframe::CreationContext<'_>| Box::new(ConfigAnalyzer::new(cc))),
     )
 }
 
+#[derive(Default)]
 struct ConfigAnalyzer {
-    url: String,
-    groups: Vec<String>,
-    password: String,
-    gitlab_client: Option<gitlab::Gitlab>,
-    data: BTreeMap<Box<str>, Project>,
-    project_configs: BTreeMap<Box<str>, VariableShareConfig>,
-    project_dependencies: Vec<(Box<str>, Box<str>)>,
+    settings: Settings,
+    /// Tokens typed into the settings panel by instance name, only kept in memory.
+    token_inputs: BTreeMap<String, String>,
+    new_group: String,
+    available_groups: Vec<Group>,
+    /// Connection results by instance name.
+    connection_status: BTreeMap<String, Result<String, String>>,
+    /// Connected clients by instance host.
+    clients: BTreeMap<String, gitlab::Gitlab>,
+    data: BTreeMap<ProjectKey, Project>,
+    project_configs: BTreeMap<ProjectKey, Vec<ConfigFile>>,
+    project_dependencies: Vec<Dependency>,
     graph: Option<Graph<ProjectNode, (), Directed>>,
+    view: ViewOptions,
+    session_name: String,
+    /// Names of the stored sessions, None until read from the sessions directory.
+    session_names: Option<Vec<String>>,
+    impact: Option<impact::Impact>,
+    diff: Option<diff::SnapshotDiff>,
+    /// Base and head ref to compare the configuration at.
+    compare_refs: (String, String),
+    /// Project and merge request iid or branch to analyze the changes of.
+    change_input: (String, String),
+    /// Findings of the last CI/CD variable audit.
+    variable_audit: Option<Vec<audit::VariableFinding>>,
+    /// Violations found by the last policy check.
+    policy_violations: Option<Vec<policy::PolicyViolation>>,
+}
+
+impl ConfigAnalyzer {
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = Self::default();
+        if let Some(session) = cc
+            .storage
+            .and_then(|storage| eframe::get_value::<Session>(storage, SESSION_KEY))
+        {
+            app.restore_session(session);
+        }
+        app
+    }
 }
 
-impl Default for ConfigAnalyzer {
-    fn default() -> Self {
+/// Identifies a project across GitLab instances, written as `host#id`.
+#[derive(Clone, Hash, std::fmt::Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProjectKey {
+    pub host: Box<str>,
+    pub id: u64,
+}
+
+impl ProjectKey {
+    pub fn new(host: &str, id: u64) -> Self {
         Self {
-            url: "".to_owned(),
-            groups: vec!["32365".to_owned(), "32366".to_owned(), "32364".to_owned(), "25429".to_owned()],
-            password: std::env::var("GITLAB_TOKEN").unwrap_or_default(),
-            gitlab_client: None,
-            data: BTreeMap::new(),
-            project_configs: BTreeMap::new(),
-            project_dependencies: Vec::new(),
-            graph: None,
+            host: host.into(),
+            id,
         }
     }
 }
 
+impl Display for ProjectKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}#{}", self.host, self.id)
+    }
+}
+
+impl FromStr for ProjectKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, id) = s
+            .rsplit_once('#')
+            .ok_or_else(|| format!("invalid project key {s}"))?;
+        let id = id
+            .parse()
+            .map_err(|e| format!("invalid project id in {s}: {e}"))?;
+        Ok(Self::new(host, id))
+    }
+}
+
+// serialized as string so it can be used as a key in json maps
+impl Serialize for ProjectKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ProjectKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Identity of a node in the dependency graph.
+#[derive(Clone, Hash, std::fmt::Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum NodeKey {
+    Project(ProjectKey),
+    /// All projects of a collapsed group.
+    Group(Box<str>),
+    /// A store shared by projects, in the store view.
+    Store(Store),
+}
+
 #[derive(Clone, Hash, std::fmt::Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ProjectNode {
-    id: Box<str>,
+    key: NodeKey,
     name: Box<str>,
+    /// Full path of the namespace.
     group: Box<str>,
 }
-impl From<Project> for ProjectNode {
-    fn from(value: Project) -> Self {
+
+impl ProjectNode {
+    pub fn new(key: ProjectKey, project: &Project) -> Self {
+        Self {
+            key: NodeKey::Project(key),
+            name: project.name.clone().into_boxed_str(),
+            group: project_group(project).into(),
+        }
+    }
+
+    pub fn group(group: &str, members: usize) -> Self {
+        Self {
+            key: NodeKey::Group(group.into()),
+            name: format!("{group} ({members})").into_boxed_str(),
+            group: group.into(),
+        }
+    }
+
+    pub fn store(store: Store) -> Self {
         Self {
-            id: value.id.to_string().into_boxed_str(),
-            name: value.name.into_boxed_str(),
-            group: value.namespace.name.into_boxed_str(),
+            name: store.to_string().into_boxed_str(),
+            group: store.kind.label().into(),
+            key: NodeKey::Store(store),
         }
     }
+
+    pub fn project_key(&self) -> Option<&ProjectKey> {
+        match &self.key {
+            NodeKey::Project(key) => Some(key),
+            NodeKey::Group(_) | NodeKey::Store(_) => None,
+        }
+    }
+
+    pub fn is_store(&self) -> bool {
+        matches!(self.key, NodeKey::Store(_))
+    }
+}
+
+/// The group or namespace a project is clustered by.
+pub fn project_group(project: &Project) -> &str {
+    &project.namespace.full_path
 }
 
 impl eframe::App for ConfigAnalyzer {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, SESSION_KEY, &self.to_session());
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         egui::SidePanel::left("controls").show(ctx, |ui: &mut egui::Ui| {
-            // let url_label = ui.label("Hostname: ");
             ui.vertical(|ui| {
-                // ui.heading("Heading 1");
-                // ui.text_edit_singleline(&mut self.url)
-                //     .labelled_by(url_label.id);
-                // ui.add(egui::TextEdit::singleline(&mut self.password).password(true));
-
-                // ui.text_edit_singleline(&mut self.group)
-                //     .labelled_by("Group".into());
-
-                // ui.add(egui::Slider::new(&mut self.age, 0..=120).text("age"));
-                if ui.button("Connect to Gitlab").clicked() {
-                    let res = gitlab::Gitlab::new(&self.url, &self.password);
-                    match &res {
-                        Ok(_) => log::info!("Connected to Gitlab"),
-                        Err(e) => log::error!("Failed to connect to Gitlab {e}"),
-                    }
-                    self.gitlab_client = res.ok();
+                self.settings_ui(ui);
+                self.sessions_ui(ui);
+                self.groups_ui(ui);
+                self.filter_ui(ui);
+                self.diff_ui(ui);
+                self.environment_ui(ui);
+                self.policy_ui(ui);
+                if !self.clients.is_empty() {
+                    self.merge_request_ui(ui);
+                    self.variable_audit_ui(ui);
                 }
-                ui.label(format!("Current Url {}", self.url));
+                egui::CollapsingHeader::new("View").show(ui, |ui| {
+                    ui.checkbox(&mut self.view.labels_always, "Always show labels");
+                    ui.checkbox(&mut self.view.fit_to_screen, "Fit to screen");
+                    ui.checkbox(&mut self.view.zoom_and_pan, "Zoom and pan");
+                    if ui
+                        .checkbox(&mut self.view.stores_as_nodes, "Show stores as nodes")
+                        .changed()
+                        && !self.data.is_empty()
+                    {
+                        self.generate_graph();
+                    }
+                    let previous = (self.view.layout, self.view.layout_seed);
+                    egui::ComboBox::from_label("Layout")
+                        .selected_text(self.view.layout.label())
+                        .show_ui(ui, |ui| {
+                            for kind in LayoutKind::ALL {
+                                ui.selectable_value(&mut self.view.layout, kind, kind.label());
+                            }
+                        });
+                    if self.view.layout == LayoutKind::ForceDirected {
+                        ui.add(egui::DragValue::new(&mut self.view.layout_seed).prefix("Seed: "));
+                    }
+                    if previous != (self.view.layout, self.view.layout_seed)
+                        || ui.button("Reset layout").clicked()
+                    {
+                        self.apply_layout();
+                    }
+                });
 
-                if let Some(client) = &self.gitlab_client {
+                if !self.clients.is_empty() {
+                    ui.checkbox(
+                        &mut self.settings.discovery.include_subgroups,
+                        "Include subgroups",
+                    );
+                    ui.checkbox(
+                        &mut self.settings.discovery.walk_subgroups,
+                        "Walk subgroups",
+                    );
+                    ui.checkbox(
+                        &mut self.settings.discovery.include_archived,
+                        "Include archived",
+                    );
+                    ui.checkbox(&mut self.settings.discovery.include_forks, "Include forks");
+                    ui.checkbox(
+                        &mut self.settings.discovery.include_shared,
+                        "Include shared",
+                    );
                     if ui.button("Load Projects in Groups").clicked() {
-                        // https://docs.gitlab.com/ee/api/projects.html
-                        for group in &self.groups {
-                            let projects_request = GroupBuilder::default()
-                                .group(group.clone())
-                                .with_projects(true)
-                                .build()
-                                .unwrap();
-                            let projects_response: Result<Group, ApiError<_>> =
-                                projects_request.query(client);
-
-                            match projects_response {
-                                Ok(g) => {
-                                    log::debug!("Group: {:?}", &g);
-
-                                    self.data
-                                        .extend(g.projects.unwrap().into_iter().map(|project| {
-                                            (project.id.to_string().into_boxed_str(), project)
-                                        }));
-                                }
-                                Err(e) => {
-                                    // ui.label(format!("Error: {}", e));
-                                    log::error!("Error: {}", e);
-                                }
-                            }
-                        }
+                        self.load_projects();
                     }
 
                     if ui.button("Load Graph Input Data").clicked() {
-                        // https://docs.gitlab.com/ee/api/repositories.html#list-repository-tree
-                        for project in self.data.iter() {
-                            let tree_object: Option<Vec<gitlab::types::RepoTreeObject>> =
-                                TreeBuilder::default()
-                                    // .path("main.tf")
-                                    .ref_("main")
-                                    .project(project.0.to_string())
-                                    .recursive(true)
-                                    .build()
-                                    .unwrap()
-                                    .query(client)
-                                    .ok();
-                            if let Some(tos) = tree_object {
-                            //    tos.iter().for_each(|o| {
-                            //         log::info!(
-                            //             "Json File: {:?} in project {}",
-                            //             &o,
-                            //             &project.1.name
-                            //         );
-                            //     });
-                                for tree_object in tos.iter().filter(|o| {
-                                    o.name.starts_with("cli-config-") || o.name.starts_with("redis") && o.name.ends_with(".json") // TODO yaml
-                                }) {
-                                    log::info!(
-                                        "Json File: {:?} in project {}",
-                                        &tree_object.path,
-                                        &project.1.name
-                                    );
-                                    let file_content: Result<File, ApiError<RestError>> =
-                                        gitlab::api::projects::repository::files::FileBuilder::default()
-                                            .project(project.0.to_string())
-                                            .file_path(tree_object.path.clone())
-                                            .ref_("main")
-                                            .build()
-                                            .unwrap()
-                                            .query(client);
-                                    match file_content {
-                                        Err(error) => {
-                                            log::error!(
-                                                "Failed to load file content for {:?} {}",
-                                                &tree_object,
-                                                error
-                                            );
-                                        }
-                                        Ok(content) => {
-                                            // log::info!("File Content: {}", &content);
-                                            let config: VariableShareConfig =
-                                                serde_json::from_str(&content.get_content()).expect("content of file is not valid json");
-                                            log::debug!("Config: {:?}", &config);
-
-                                            self.project_configs.insert(project.0.clone(), config);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        self.update_project_dependencies();
-                        self.generate_graph();
+                        self.load_graph_input_data();
                     }
                 }
 
                 ui.label(format!("Projects: {}", &self.data.len()));
-                for project in &self.data {
+                for (key, project) in &self.data {
                     ui.label(format!(
                         "Project Name: {}, Project ID: {}",
-                        project.1.name, project.1.id
+                        project.name, key
                     ));
                 }
             });
         });
+        let selected = self.selected_projects();
+        if !selected.is_empty() {
+            egui::SidePanel::right("details").show(ctx, |ui: &mut egui::Ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for key in &selected {
+                        self.details_ui(ui, key);
+                    }
+                });
+            });
+        }
         egui::CentralPanel::default().show(ctx, |ui: &mut egui::Ui| {
-            if let Some(mut graph) = self.graph.as_mut() {
+            if let Some(graph) = self.graph.as_mut() {
                 let interaction_settings = &SettingsInteraction::new()
                     .with_dragging_enabled(true)
                     .with_clicking_enabled(true)
                     .with_selection_enabled(true)
                     .with_selection_multi_enabled(true);
-                ui.add(&mut GraphView::new(&mut graph).with_interactions(interaction_settings));
+                let navigation_settings = &SettingsNavigation::new()
+                    .with_fit_to_screen_enabled(self.view.fit_to_screen)
+                    .with_zoom_and_pan_enabled(self.view.zoom_and_pan);
+                let style_settings =
+                    &SettingsStyle::new().with_labels_always(self.view.labels_always);
+                let mut view = GraphView::new(graph)
+                    .with_interactions(interaction_settings)
+                    .with_navigations(navigation_settings)
+                    .with_styles(style_settings);
+                if self.view.color_by_group {
+                    view = if self.view.labels_always {
+                        view.with_custom_node_draw(group_node_draw!(true))
+                    } else {
+                        view.with_custom_node_draw(group_node_draw!(false))
+                    };
+                }
+                let response = ui.add(&mut view);
+                if self.view.group_boxes {
+                    draw_group_boxes(ui.painter(), graph, &Metadata::get(ui));
+                }
+                response.context_menu(|ui| self.node_menu_ui(ui));
             }
         });
+        self.impact_ui(ctx);
+        self.diff_window_ui(ctx);
     }
 }
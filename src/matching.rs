@@ -0,0 +1,176 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configuration_schema::{split_secret_version, Task},
+    graph::StoreKind,
+};
+
+/// A key a reader requests matched with a key a writer stores.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct KeyMatch {
+    pub requested: String,
+    pub stored: String,
+}
+
+impl Display for KeyMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.requested == self.stored {
+            write!(f, "{}", self.requested)
+        } else {
+            write!(f, "{} ← {}", self.requested, self.stored)
+        }
+    }
+}
+
+/// Whether two keys can refer to the same value, either of them possibly a pattern.
+///
+/// `*` matches any number of characters and `?` a single one, so `app:*` is a prefix.
+pub fn key_matches(requested: &str, stored: &str) -> bool {
+    requested == stored || glob_match(requested, stored) || glob_match(stored, requested)
+}
+
+/// [key_matches] for keys of a store of `kind`, ignoring pinned Key Vault versions.
+pub fn store_key_matches(kind: StoreKind, requested: &str, stored: &str) -> bool {
+    match kind {
+        StoreKind::KeyVault => key_matches(
+            split_secret_version(requested).0,
+            split_secret_version(stored).0,
+        ),
+        _ => key_matches(requested, stored),
+    }
+}
+
+/// Matches `text` against a pattern of `*` and `?` wildcards.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), text.chars().collect());
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` and the text position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// A Key Vault URL without surrounding whitespace, trailing slashes and case differences.
+pub fn normalize_vault_url(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_lowercase()
+}
+
+/// Keys a task reads from its source, before mapping.
+pub fn requested_keys(task: &Task) -> impl Iterator<Item = &str> {
+    task.mapping.keys().map(String::as_str)
+}
+
+/// Keys a task writes to its target, after mapping.
+pub fn stored_keys(task: &Task) -> impl Iterator<Item = &str> {
+    task.mapping.values().flatten().map(|target| target.key())
+}
+
+/// Keys `reader` requests which `writer` stores, sorted.
+pub fn match_keys(reader: &Task, writer: &Task) -> Vec<KeyMatch> {
+    match_keys_by(reader, writer, key_matches)
+}
+
+/// Key Vault objects `reader` requests which `writer` stores, a pinned version matching any.
+pub fn match_secrets(reader: &Task, writer: &Task) -> Vec<KeyMatch> {
+    match_keys_by(reader, writer, |requested, stored| {
+        store_key_matches(StoreKind::KeyVault, requested, stored)
+    })
+}
+
+fn match_keys_by(
+    reader: &Task,
+    writer: &Task,
+    matches: impl Fn(&str, &str) -> bool + Copy,
+) -> Vec<KeyMatch> {
+    let mut matches: Vec<KeyMatch> = requested_keys(reader)
+        .flat_map(|requested| {
+            stored_keys(writer)
+                .filter(move |stored| matches(requested, stored))
+                .map(move |stored| KeyMatch {
+                    requested: requested.to_owned(),
+                    stored: stored.to_owned(),
+                })
+        })
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_match, key_matches, match_keys, store_key_matches, KeyMatch};
+    use crate::{graph::StoreKind, loader::test_config_file};
+
+    #[test]
+    pub fn glob_patterns() {
+        assert!(glob_match("app:*", "app:db:url"));
+        assert!(glob_match("*:url", "app:db:url"));
+        assert!(glob_match("app:?b:*", "app:db:url"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("app:*", "other:db"));
+        assert!(!glob_match("app", "app:db"));
+        assert!(key_matches("app:db", "app:*"));
+    }
+
+    #[test]
+    pub fn pinned_versions_match_key_vault_objects() {
+        let pinned = "db-password/0123456789abcdef0123456789abcdef";
+        assert!(store_key_matches(
+            StoreKind::KeyVault,
+            pinned,
+            "db-password"
+        ));
+        assert!(!store_key_matches(StoreKind::Redis, pinned, "db-password"));
+    }
+
+    #[test]
+    pub fn keys_are_matched_after_mapping() {
+        let config = test_config_file(
+            "cli-config-test.json",
+            r#"[{"source": {"type": "Environment"},
+                "target": {"type": "Redis", "hostname": "cache"},
+                "mapping": {"DB_URL": ["app:db:url", {"key": "app:db:host", "function": "host"}]}},
+               {"source": {"type": "Redis", "hostname": "cache"},
+                "target": {"type": "ProcessEnvironment"},
+                "mapping": {"app:db:*": ["DB"], "DB_URL": ["URL"]}}]"#,
+        )
+        .config;
+        let (writer, reader) = (&config.tasks[0], &config.tasks[1]);
+
+        let matched = |requested: &str, stored: &str| KeyMatch {
+            requested: requested.to_owned(),
+            stored: stored.to_owned(),
+        };
+        // the writer's source key DB_URL is not what it stores
+        assert_eq!(
+            match_keys(reader, writer),
+            vec![
+                matched("app:db:*", "app:db:host"),
+                matched("app:db:*", "app:db:url")
+            ]
+        );
+    }
+}
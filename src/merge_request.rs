@@ -0,0 +1,112 @@
+use eframe::egui;
+use gitlab::api::{projects::merge_requests::MergeRequest as MergeRequestEndpoint, Query};
+
+use crate::{
+    diff::SnapshotDiff, gitlab_merge_request::MergeRequest, loader::project_config_files,
+    ConfigAnalyzer, ProjectKey,
+};
+
+/// The changes to analyze: a merge request or a branch of a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    MergeRequest(u64),
+    Branch(String),
+}
+
+impl super::ConfigAnalyzer {
+    /// Overlays the configuration files of the changed branch onto the loaded analysis.
+    pub(crate) fn analyze_change(
+        &self,
+        project: &ProjectKey,
+        change: &Change,
+    ) -> Result<SnapshotDiff, String> {
+        let client = self
+            .clients
+            .get(&*project.host)
+            .ok_or_else(|| format!("Not connected to {}", project.host))?;
+        let (source, branch, name) = match change {
+            Change::MergeRequest(iid) => {
+                let merge_request: MergeRequest = MergeRequestEndpoint::builder()
+                    .project(project.id)
+                    .merge_request(*iid)
+                    .build()
+                    .unwrap()
+                    .query(client)
+                    .map_err(|e| format!("Failed to get merge request !{iid}: {e}"))?;
+                log::info!("Merge request !{iid}: {}", merge_request.title);
+                (
+                    ProjectKey::new(&project.host, merge_request.source_project_id),
+                    merge_request.source_branch,
+                    format!("!{iid}"),
+                )
+            }
+            Change::Branch(branch) => (project.clone(), branch.clone(), branch.clone()),
+        };
+
+        let mut project_configs = self.project_configs.clone();
+        let mut files =
+            project_config_files(client, source.id, &self.project_name(project), &branch)?;
+        self.load_terraform_states(project, &mut files);
+        if files.is_empty() {
+            project_configs.remove(project);
+        } else {
+            project_configs.insert(project.clone(), files);
+        }
+        let head = ConfigAnalyzer::analysis(self.data.clone(), project_configs);
+        let base_name = self
+            .project_configs
+            .get(project)
+            .and_then(|files| files.first())
+            .map_or(crate::loader::CONFIG_REF, |file| &file.ref_);
+        Ok(SnapshotDiff::new(base_name, self, &name, &head))
+    }
+
+    pub(crate) fn merge_request_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Merge request").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Project:");
+                ui.text_edit_singleline(&mut self.change_input.0)
+                    .on_hover_text("host#id");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Merge request iid or branch:");
+                ui.text_edit_singleline(&mut self.change_input.1);
+            });
+            if ui.button("Analyze changes").clicked() {
+                let (project, change) = &self.change_input;
+                let result = project
+                    .trim()
+                    .parse::<ProjectKey>()
+                    .and_then(|project| self.analyze_change(&project, &parse_change(change)));
+                match result {
+                    Ok(diff) => self.diff = Some(diff),
+                    Err(e) => log::error!("{e}"),
+                }
+            }
+        });
+    }
+}
+
+/// A number is a merge request iid, anything else a branch.
+pub fn parse_change(input: &str) -> Change {
+    let input = input.trim();
+    match input.trim_start_matches('!').parse() {
+        Ok(iid) => Change::MergeRequest(iid),
+        Err(_) => Change::Branch(input.to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_change, Change};
+
+    #[test]
+    pub fn numbers_are_merge_requests() {
+        assert_eq!(parse_change(" 42 "), Change::MergeRequest(42));
+        assert_eq!(parse_change("!7"), Change::MergeRequest(7));
+        assert_eq!(
+            parse_change("feature/42"),
+            Change::Branch("feature/42".to_owned())
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use std::{collections::BTreeMap, fmt::Display, fs, path::Path};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configuration_schema::{type_name, SourceConfig, TargetConfig},
+    environment::{declared_environment, EnvironmentPattern},
+    graph::{resolve_project, target_store, StoreKind},
+    loader::ConfigFile,
+    matching::{glob_match, normalize_vault_url},
+    project_group, ProjectKey,
+};
+
+/// Conventions the configuration files of all projects are checked against.
+///
+/// Patterns use `*` and `?` wildcards.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Policy {
+    pub rules: Vec<PolicyRule>,
+}
+
+/// Conventions for the projects of some groups.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PolicyRule {
+    /// Patterns of the group paths the rule applies to, all projects when empty.
+    pub groups: Vec<String>,
+    /// Only these source → target combinations may be used, any when empty.
+    pub allow: Vec<FlowPattern>,
+    /// These source → target combinations must not be used.
+    pub deny: Vec<FlowPattern>,
+    /// Redis sources and targets must name the service principal they authenticate as.
+    pub redis_requires_sp_object_id: bool,
+    /// Terraform states must be read from the project itself.
+    pub terraform_state_from_same_project: bool,
+    /// Patterns of the Key Vault URLs which may be used, by environment.
+    ///
+    /// `*` applies to files of any environment without an entry of their own.
+    pub keyvault_urls: BTreeMap<String, Vec<String>>,
+    /// Patterns keys written to stores of a kind must match.
+    pub key_names: BTreeMap<StoreKind, Vec<String>>,
+}
+
+/// A source → target combination, matched against the type names of the configuration.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FlowPattern {
+    pub source: String,
+    pub target: String,
+}
+
+impl FlowPattern {
+    fn matches(&self, source: &str, target: &str) -> bool {
+        glob_match(&self.source, source) && glob_match(&self.target, target)
+    }
+}
+
+impl Display for FlowPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} → {}", self.source, self.target)
+    }
+}
+
+impl Policy {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read policy {}: {e}", path.display()))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("{} is not a valid policy: {e}", path.display()))
+    }
+}
+
+/// A task breaking a rule of the policy.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PolicyViolation {
+    pub project: ProjectKey,
+    pub file: String,
+    /// Number of the task in the file, starting at 1.
+    pub task: usize,
+    pub message: String,
+}
+
+impl Display for PolicyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Task {}: {} in {} ({})",
+            self.task, self.message, self.project, self.file
+        )
+    }
+}
+
+/// Violations of `rule` by the tasks of `file` of `project`, by task number.
+pub fn check_rule(
+    rule: &PolicyRule,
+    environments: &[EnvironmentPattern],
+    project: &ProjectKey,
+    file: &ConfigFile,
+) -> Vec<(usize, String)> {
+    let mut violations = Vec::new();
+    for (index, task) in file.config.tasks.iter().enumerate() {
+        let task_number = index + 1;
+        // the environment must not be inferred from the Key Vault URLs being checked
+        let environment = declared_environment(environments, file, task);
+        let mut violation = |message: String| violations.push((task_number, message));
+        let (source, target) = (type_name(&task.source), type_name(&task.target));
+
+        if !rule.allow.is_empty() && !rule.allow.iter().any(|f| f.matches(&source, &target)) {
+            violation(format!("{source} → {target} is not allowed"));
+        }
+        if let Some(denied) = rule.deny.iter().find(|f| f.matches(&source, &target)) {
+            violation(format!("{source} → {target} is denied by {denied}"));
+        }
+
+        if rule.redis_requires_sp_object_id {
+            let source_sp = match &task.source {
+                SourceConfig::Redis { sp_object_id, .. } => Some(sp_object_id),
+                _ => None,
+            };
+            let target_sp = match &task.target {
+                TargetConfig::Redis { sp_object_id, .. } => Some(sp_object_id),
+                _ => None,
+            };
+            if source_sp.into_iter().chain(target_sp).any(Option::is_none) {
+                violation("Redis without sp_object_id".to_owned());
+            }
+        }
+
+        if rule.terraform_state_from_same_project {
+            if let SourceConfig::GitlabProjectTerraformState(c) = &task.source {
+                let state_project = resolve_project(project, c);
+                if state_project != *project {
+                    violation(format!("terraform state read from {state_project}"));
+                }
+            }
+        }
+
+        let allowed_urls = environment
+            .and_then(|environment| rule.keyvault_urls.get(environment))
+            .or_else(|| rule.keyvault_urls.get("*"));
+        if let Some(allowed_urls) = allowed_urls {
+            let mut urls = Vec::new();
+            if let SourceConfig::AzureKeyvault(c) = &task.source {
+                urls.push(&c.keyvault_url);
+            }
+            if let TargetConfig::AzureKeyvault(c) = &task.target {
+                urls.push(&c.keyvault_url);
+            }
+            for url in urls {
+                let url = normalize_vault_url(url);
+                if !allowed_urls
+                    .iter()
+                    .any(|allowed| glob_match(&normalize_vault_url(allowed), &url))
+                {
+                    violation(format!(
+                        "Key Vault {url} is not allowed in environment {}",
+                        environment.unwrap_or("*")
+                    ));
+                }
+            }
+        }
+
+        if let Some(store) = target_store(project, &task.target) {
+            if let Some(patterns) = rule.key_names.get(&store.kind) {
+                for key in task.mapping.values().flatten().map(|target| target.key()) {
+                    if !patterns.iter().any(|pattern| glob_match(pattern, key)) {
+                        violation(format!(
+                            "key {key} does not follow the naming convention for {}",
+                            store.kind.label()
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    violations
+}
+
+impl super::ConfigAnalyzer {
+    /// Violations of `policy` by the configuration files of all projects.
+    pub(crate) fn policy_violations(&self, policy: &Policy) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+        for (project, files) in &self.project_configs {
+            let group = self.data.get(project).map(project_group);
+            let rules = policy.rules.iter().filter(|rule| {
+                rule.groups.is_empty()
+                    || group.is_some_and(|group| {
+                        rule.groups.iter().any(|pattern| glob_match(pattern, group))
+                    })
+            });
+            for rule in rules {
+                for file in files {
+                    for (task, message) in
+                        check_rule(rule, &self.settings.environments, project, file)
+                    {
+                        violations.push(PolicyViolation {
+                            project: project.clone(),
+                            file: file.path.clone(),
+                            task,
+                            message,
+                        });
+                    }
+                }
+            }
+        }
+        violations.sort();
+        violations.dedup();
+        violations
+    }
+
+    pub(crate) fn policy_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Policy").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Policy file:");
+                let mut path = self.settings.policy_file.display().to_string();
+                if ui.text_edit_singleline(&mut path).changed() {
+                    self.settings.policy_file = path.into();
+                }
+            });
+            if ui.button("Check policy").clicked() {
+                match Policy::open(&self.settings.policy_file) {
+                    Ok(policy) => self.policy_violations = Some(self.policy_violations(&policy)),
+                    Err(e) => log::error!("{e}"),
+                }
+            }
+            match &self.policy_violations {
+                Some(violations) if violations.is_empty() => {
+                    ui.label("No violations.");
+                }
+                Some(violations) => {
+                    for violation in violations {
+                        ui.colored_label(ui.visuals().warn_fg_color, violation.to_string());
+                    }
+                }
+                None => {}
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Policy;
+    use crate::{loader::test_config_file, ConfigAnalyzer, ProjectKey};
+
+    #[test]
+    pub fn violations_of_policy_rules() {
+        let policy: Policy = serde_json::from_str(
+            r#"{"rules": [{
+                "deny": [{"source": "GitlabProjectVariables", "target": "*"}],
+                "redisRequiresSpObjectId": true,
+                "terraformStateFromSameProject": true,
+                "keyvaultUrls": {"prod": ["https://*-prod.vault.azure.net"]},
+                "keyNames": {"Redis": ["app:*"]}
+            }, {
+                "groups": ["other/*"],
+                "allow": [{"source": "Environment", "target": "*"}]
+            }]}"#,
+        )
+        .unwrap();
+        let mut app = ConfigAnalyzer::default();
+        app.project_configs.insert(
+            ProjectKey::new("gitlab.com", 1),
+            vec![test_config_file(
+                "cli-config-prod.json",
+                r#"[{"source": {"type": "GitlabProjectVariables", "project_id": 1, "environment": "prod"},
+                    "target": {"type": "Redis", "hostname": "cache", "sp_object_id": "sp"},
+                    "mapping": {"URL": ["app:url", "url"]}},
+                   {"source": {"type": "AzureKeyvault", "url": "https://app-prod.vault.azure.net/", "secretType": "secret"},
+                    "target": {"type": "AzureKeyvault", "url": "https://app-dev.vault.azure.net", "secretType": "secret"},
+                    "mapping": {"db": ["db"]}},
+                   {"source": {"type": "GitlabProjectTerraformState", "project_id": 2},
+                    "target": {"type": "Redis", "hostname": "cache"},
+                    "mapping": {"host": ["app:host"]}}]"#,
+            )],
+        );
+
+        let violations: Vec<_> = app
+            .policy_violations(&policy)
+            .into_iter()
+            .map(|v| format!("{} {}", v.task, v.message))
+            .collect();
+        assert_eq!(
+            violations,
+            vec![
+                "1 GitlabProjectVariables → Redis is denied by GitlabProjectVariables → *",
+                "1 key url does not follow the naming convention for Redis",
+                "2 Key Vault https://app-dev.vault.azure.net is not allowed in environment prod",
+                "3 Redis without sp_object_id",
+                "3 terraform state read from gitlab.com#2",
+            ]
+        );
+
+        // a dev Key Vault in a prod file is checked against the prod URLs
+        app.project_configs.insert(
+            ProjectKey::new("gitlab.com", 3),
+            vec![test_config_file(
+                "deploy/cli-config-api-prod.json",
+                r#"[{"source": {"type": "AzureKeyvault", "url": "https://api-dev.vault.azure.net", "secretType": "secret"},
+                    "target": {"type": "ProcessEnvironment"},
+                    "mapping": {"db": ["DB"]}}]"#,
+            )],
+        );
+        let violations: Vec<_> = app
+            .policy_violations(&policy)
+            .into_iter()
+            .filter(|v| v.project == ProjectKey::new("gitlab.com", 3))
+            .map(|v| format!("{} {}", v.task, v.message))
+            .collect();
+        assert_eq!(
+            violations,
+            vec!["1 Key Vault https://api-dev.vault.azure.net is not allowed in environment prod"]
+        );
+    }
+}
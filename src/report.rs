@@ -0,0 +1,289 @@
+use gitlab::{
+    api::{
+        paged,
+        projects::merge_requests::notes::{
+            CreateMergeRequestNote, EditMergeRequestNote, MergeRequestNotes,
+        },
+        ApiError, Pagination, Query,
+    },
+    RestError,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{clusters::stable_hash, diff::SnapshotDiff, gitlab_merge_request::Note, ProjectKey};
+
+/// Marks the summary note so later runs update it instead of adding another one.
+pub const NOTE_MARKER: &str = "<!-- config-analyzer -->";
+
+/// An issue of a GitLab Code Quality report.
+///
+/// https://docs.gitlab.com/ee/ci/testing/code_quality.html#implement-a-custom-tool
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CodeQualityIssue {
+    pub description: String,
+    pub check_name: String,
+    pub fingerprint: String,
+    pub severity: Severity,
+    pub location: Location,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Minor,
+    Major,
+    Critical,
+    Blocker,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: String,
+    pub lines: Lines,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Lines {
+    pub begin: u64,
+}
+
+impl CodeQualityIssue {
+    fn new(check_name: &str, description: String, severity: Severity, path: &str) -> Self {
+        Self {
+            fingerprint: format!(
+                "{:016x}",
+                stable_hash(&format!("{check_name} {description}"))
+            ),
+            check_name: check_name.to_owned(),
+            description,
+            severity,
+            location: Location {
+                path: path.to_owned(),
+                lines: Lines { begin: 1 },
+            },
+        }
+    }
+}
+
+/// The problems introduced by the head of `diff` in the files of `project` as Code Quality
+/// issues.
+///
+/// Issues need a file of the repository the report belongs to, problems in other projects
+/// are only part of the summary note. Cycles have no file of their own and are reported on
+/// the first changed configuration file of `project`, or not at all without one.
+pub fn code_quality(diff: &SnapshotDiff, project: &ProjectKey) -> Vec<CodeQualityIssue> {
+    let mut issues = Vec::new();
+    for unresolved in diff.new_unresolved.iter().filter(|u| u.project == *project) {
+        issues.push(CodeQualityIssue::new(
+            "unresolved-variable",
+            format!("Unresolved variable {unresolved}"),
+            Severity::Major,
+            &unresolved.file,
+        ));
+    }
+    for problem in diff
+        .new_config_problems
+        .iter()
+        .filter(|p| p.project == *project)
+    {
+        issues.push(CodeQualityIssue::new(
+            "invalid-config",
+            format!("Invalid configuration: {problem}"),
+            Severity::Major,
+            &problem.file,
+        ));
+    }
+    for problem in diff
+        .new_terraform_problems
+        .iter()
+        .filter(|p| p.project == *project)
+    {
+        issues.push(CodeQualityIssue::new(
+            "terraform-output",
+            format!("Terraform output problem: {problem}"),
+            Severity::Major,
+            &problem.file,
+        ));
+    }
+    for flow in diff
+        .new_sensitive_flows
+        .iter()
+        .filter(|f| f.project == *project)
+    {
+        issues.push(CodeQualityIssue::new(
+            "exposed-sensitive-value",
+            format!("Sensitive value exposed: {flow}"),
+            Severity::Critical,
+            &flow.file,
+        ));
+    }
+    for secret in diff.new_secrets.iter().filter(|s| s.project == *project) {
+        issues.push(CodeQualityIssue::new(
+            "hardcoded-secret",
+            format!("Possible secret: {secret}"),
+            secret.severity(),
+            &secret.file,
+        ));
+    }
+    let cycle_path = diff
+        .changed_tasks
+        .iter()
+        .find(|change| change.project == *project)
+        .map(|change| change.file.as_str());
+    if let Some(path) = cycle_path {
+        for cycle in &diff.new_cycles {
+            issues.push(CodeQualityIssue::new(
+                "dependency-cycle",
+                format!("Dependency cycle {cycle}"),
+                Severity::Critical,
+                path,
+            ));
+        }
+    }
+    issues
+}
+
+/// Posts `body` as the summary note of a merge request, updating the note of an earlier run.
+pub fn post_summary_note(
+    client: &gitlab::Gitlab,
+    project: u64,
+    merge_request: u64,
+    body: &str,
+) -> Result<Note, ApiError<RestError>> {
+    let body = format!("{NOTE_MARKER}\n{body}");
+    let endpoint = MergeRequestNotes::builder()
+        .project(project)
+        .merge_request(merge_request)
+        .build()
+        .unwrap();
+    let notes: Vec<Note> = paged(endpoint, Pagination::All).query(client)?;
+    match notes.iter().find(|note| note.body.starts_with(NOTE_MARKER)) {
+        Some(note) => EditMergeRequestNote::builder()
+            .project(project)
+            .merge_request(merge_request)
+            .note(note.id)
+            .body(body)
+            .build()
+            .unwrap()
+            .query(client),
+        None => CreateMergeRequestNote::builder()
+            .project(project)
+            .merge_request(merge_request)
+            .body(body)
+            .build()
+            .unwrap()
+            .query(client),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::Matcher;
+
+    use super::{code_quality, post_summary_note, Severity, NOTE_MARKER};
+    use crate::{
+        diff::{SnapshotDiff, TaskChange},
+        graph::{Store, StoreKind},
+        lint::UnresolvedVariable,
+        settings::test_client,
+        ProjectKey,
+    };
+
+    #[test]
+    pub fn code_quality_issues_of_new_problems() {
+        let (project, other) = (
+            ProjectKey::new("gitlab.com", 1),
+            ProjectKey::new("gitlab.com", 2),
+        );
+        let change = |project: &ProjectKey, file: &str| TaskChange {
+            project: project.clone(),
+            file: file.to_owned(),
+            task: "Environment → Redis cache".to_owned(),
+            added: vec!["url → url".to_owned()],
+            removed: Vec::new(),
+        };
+        let unresolved = |project: &ProjectKey, file: &str| UnresolvedVariable {
+            project: project.clone(),
+            file: file.to_owned(),
+            store: Store::new(StoreKind::Redis, "cache"),
+            key: "url".to_owned(),
+        };
+        let mut diff = SnapshotDiff {
+            changed_tasks: vec![
+                change(&other, "cli-config-other.json"),
+                change(&project, "cli-config-writer.json"),
+            ],
+            new_unresolved: vec![
+                unresolved(&project, "cli-config-app.json"),
+                unresolved(&other, "cli-config-other.json"),
+            ],
+            new_cycles: vec!["gitlab.com#1 → gitlab.com#2 → gitlab.com#1".to_owned()],
+            ..Default::default()
+        };
+
+        let issues = code_quality(&diff, &project);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].check_name, "unresolved-variable");
+        assert_eq!(issues[0].location.path, "cli-config-app.json");
+        assert_eq!(issues[1].severity, Severity::Critical);
+        assert_eq!(issues[1].location.path, "cli-config-writer.json");
+        assert_ne!(issues[0].fingerprint, issues[1].fingerprint);
+        assert_eq!(code_quality(&diff, &project), issues);
+        let json = serde_json::to_value(&issues).unwrap();
+        assert_eq!(json[0]["severity"], "major");
+        assert_eq!(json[0]["location"]["lines"]["begin"], 1);
+
+        // without a changed file of the project the cycle is only in the note
+        diff.changed_tasks.remove(1);
+        let issues = code_quality(&diff, &project);
+        assert_eq!(issues.len(), 1);
+        assert!(diff.to_markdown().contains("New dependency cycles"));
+    }
+
+    #[test]
+    pub fn summary_note_is_updated() {
+        let mut server = mockito::Server::new();
+        let client = test_client(&mut server);
+        let notes = server
+            .mock("GET", "/api/v4/projects/1/merge_requests/2/notes")
+            .match_query(Matcher::Any)
+            .with_body(format!(
+                r#"[{{"id": 4, "body": "looks good"}}, {{"id": 5, "body": "{NOTE_MARKER}\nold"}}]"#
+            ))
+            .create();
+        let edit = server
+            .mock("PUT", "/api/v4/projects/1/merge_requests/2/notes/5")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::UrlEncoded(
+                "body".to_owned(),
+                format!("{NOTE_MARKER}\nnew"),
+            ))
+            .with_body(format!(r#"{{"id": 5, "body": "{NOTE_MARKER}\nnew"}}"#))
+            .create();
+
+        let note = post_summary_note(&client, 1, 2, "new").unwrap();
+        assert_eq!(note.id, 5);
+        notes.assert();
+        edit.assert();
+    }
+
+    #[test]
+    pub fn summary_note_is_created() {
+        let mut server = mockito::Server::new();
+        let client = test_client(&mut server);
+        server
+            .mock("GET", "/api/v4/projects/1/merge_requests/2/notes")
+            .match_query(Matcher::Any)
+            .with_body(r#"[{"id": 4, "body": "looks good"}]"#)
+            .create();
+        let create = server
+            .mock("POST", "/api/v4/projects/1/merge_requests/2/notes")
+            .match_query(Matcher::Any)
+            .with_body(format!(r#"{{"id": 6, "body": "{NOTE_MARKER}\nnew"}}"#))
+            .create();
+
+        assert_eq!(post_summary_note(&client, 1, 2, "new").unwrap().id, 6);
+        create.assert();
+    }
+}
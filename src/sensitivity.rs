@@ -0,0 +1,231 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configuration_schema::{SourceConfig, TargetConfig, Task},
+    graph::{keyvault_store, resolve_project, source_store, target_store, Store},
+    loader::ConfigFile,
+    matching::store_key_matches,
+    terraform::{state_key, state_name, TerraformOutputs},
+    ProjectKey,
+};
+
+/// A sensitive value a task writes where it is not kept secret.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SensitiveFlow {
+    pub project: ProjectKey,
+    pub file: String,
+    /// The key the task reads.
+    pub key: String,
+    /// Where the value became sensitive, e.g. a Key Vault secret.
+    pub origin: String,
+    pub target: String,
+    pub target_key: String,
+}
+
+impl Display for SensitiveFlow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} from {} written to {} {} in {} ({})",
+            self.key, self.origin, self.target, self.target_key, self.project, self.file
+        )
+    }
+}
+
+/// Whether values written to `target` under `key` are kept secret.
+///
+/// Process environments and commands only hold values while the tool runs. Values written
+/// to Redis stay sensitive for the tasks reading them, and GitLab variables are only secret
+/// when masked.
+pub fn is_secret_target(target: &TargetConfig, key: &str) -> bool {
+    match target {
+        TargetConfig::AzureKeyvault(_)
+        | TargetConfig::ProcessEnvironment {}
+        | TargetConfig::Command {}
+        | TargetConfig::KubeConfig
+        | TargetConfig::Redis { .. } => true,
+        TargetConfig::GitlabProjectVariables { details, .. } => details
+            .as_ref()
+            .and_then(|details| details.masked_variables.as_ref())
+            .is_some_and(|masked| masked.iter().any(|variable| variable == key)),
+        TargetConfig::GlobalEnvironment {}
+        | TargetConfig::StdOutEnvironment {}
+        | TargetConfig::EnvFile { .. }
+        | TargetConfig::File => false,
+    }
+}
+
+/// Whether values written to `target` under `key` can be read outside of secret stores.
+pub fn exposes(target: &TargetConfig, key: &str) -> bool {
+    !is_secret_target(target, key)
+}
+
+/// Keys of stores holding sensitive values, with the origin of each.
+type SensitiveKeys = BTreeMap<(Store, String), String>;
+
+impl super::ConfigAnalyzer {
+    /// Sensitive values which reach targets exposing them.
+    ///
+    /// Values are sensitive when read from Key Vault, from sensitive Terraform outputs or from
+    /// masked GitLab variables. The sensitivity follows the mappings through every store the
+    /// values are written to, so a secret copied to Redis stays sensitive for its readers.
+    pub(crate) fn sensitive_flows(&self) -> BTreeSet<SensitiveFlow> {
+        let mut sensitive = self.masked_variables();
+        loop {
+            let mut flows = BTreeSet::new();
+            let mut changed = false;
+            for (owner, files) in &self.project_configs {
+                for file in files {
+                    for task in &file.config.tasks {
+                        for (key, targets) in &task.mapping {
+                            let Some(origin) = sensitive_origin(owner, file, task, key, &sensitive)
+                            else {
+                                continue;
+                            };
+                            let store = target_store(owner, &task.target);
+                            for target_key in targets.iter().map(|target| target.key()) {
+                                if let Some(store) = &store {
+                                    changed |= sensitive
+                                        .insert(
+                                            (store.clone(), target_key.to_owned()),
+                                            origin.clone(),
+                                        )
+                                        .is_none();
+                                }
+                                if exposes(&task.target, target_key) {
+                                    flows.insert(SensitiveFlow {
+                                        project: owner.clone(),
+                                        file: file.path.clone(),
+                                        key: key.clone(),
+                                        origin: origin.clone(),
+                                        target: task.target.to_string(),
+                                        target_key: target_key.to_owned(),
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            if !changed {
+                return flows;
+            }
+        }
+    }
+
+    /// GitLab variables the analyzed tasks write masked.
+    fn masked_variables(&self) -> SensitiveKeys {
+        let mut masked = SensitiveKeys::new();
+        for (owner, files) in &self.project_configs {
+            for task in crate::loader::tasks(files) {
+                let TargetConfig::GitlabProjectVariables { config, details } = &task.target else {
+                    continue;
+                };
+                let Some(variables) = details.as_ref().and_then(|d| d.masked_variables.as_ref())
+                else {
+                    continue;
+                };
+                let Some(store) = target_store(owner, &task.target) else {
+                    continue;
+                };
+                for variable in variables {
+                    let origin = format!(
+                        "masked variable {variable} of {}",
+                        resolve_project(owner, config)
+                    );
+                    masked.insert((store.clone(), variable.clone()), origin);
+                }
+            }
+        }
+        masked
+    }
+}
+
+/// Why the value `key` a task reads is sensitive, if it is.
+fn sensitive_origin(
+    owner: &ProjectKey,
+    file: &ConfigFile,
+    task: &Task,
+    key: &str,
+    sensitive: &SensitiveKeys,
+) -> Option<String> {
+    let sensitive_output = |outputs: Option<&TerraformOutputs>, source: String| {
+        outputs
+            .and_then(|outputs| outputs.get(key))
+            .filter(|output| output.sensitive)
+            .map(|_| format!("sensitive output {key} of {source}"))
+    };
+    match &task.source {
+        SourceConfig::AzureKeyvault(config) => Some(format!("{} {key}", keyvault_store(config))),
+        SourceConfig::TerraformFile(terraform) => {
+            let name = terraform.file_name.to_string_lossy().into_owned();
+            sensitive_output(file.terraform_outputs.get(&name), name)
+        }
+        SourceConfig::GitlabProjectTerraformState(config) => {
+            let (project, name) = (resolve_project(owner, config), state_name(config));
+            sensitive_output(
+                file.terraform_states.get(&state_key(&project, name)),
+                format!("terraform state {name} of {project}"),
+            )
+        }
+        source => {
+            let store = source_store(owner, source)?;
+            sensitive
+                .iter()
+                .find(|((s, stored), _)| *s == store && store_key_matches(store.kind, key, stored))
+                .map(|(_, origin)| origin.clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{loader::test_config_file, ConfigAnalyzer, ProjectKey};
+
+    #[test]
+    pub fn secrets_are_followed_through_stores() {
+        let mut app = ConfigAnalyzer::default();
+        app.project_configs.insert(
+            ProjectKey::new("gitlab.com", 1),
+            vec![test_config_file(
+                "cli-config-writer.json",
+                r#"[{"source": {"type": "AzureKeyvault", "url": "https://kv.vault.azure.net", "secretType": "secret"},
+                    "target": {"type": "Redis", "hostname": "cache"},
+                    "mapping": {"db-password": ["app:db:password"]}},
+                   {"source": {"type": "Environment"},
+                    "target": {"type": "Redis", "hostname": "cache"},
+                    "mapping": {"DB_HOST": ["app:db:host"]}}]"#,
+            )],
+        );
+        app.project_configs.insert(
+            ProjectKey::new("gitlab.com", 2),
+            vec![test_config_file(
+                "cli-config-reader.json",
+                r#"[{"source": {"type": "Redis", "hostname": "cache"},
+                    "target": {"type": "EnvFile", "file": ".env"},
+                    "mapping": {"app:db:*": ["DB"]}},
+                   {"source": {"type": "Redis", "hostname": "cache"},
+                    "target": {"type": "ProcessEnvironment"},
+                    "mapping": {"app:db:password": ["DB_PASSWORD"]}},
+                   {"source": {"type": "Redis", "hostname": "cache"},
+                    "target": {"type": "StdOutEnvironment"},
+                    "mapping": {"app:db:host": ["DB_HOST"]}}]"#,
+            )],
+        );
+
+        let flows: Vec<_> = app
+            .sensitive_flows()
+            .into_iter()
+            .map(|flow| flow.to_string())
+            .collect();
+        assert_eq!(
+            flows,
+            vec!["app:db:* from Key Vault https://kv.vault.azure.net db-password written to EnvFile .env DB in gitlab.com#2 (cli-config-reader.json)"]
+        );
+    }
+}
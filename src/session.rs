@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eframe::{egui, epaint::Vec2};
+use gitlab::Project;
+use serde::{Deserialize, Serialize};
+
+use crate::{graph::ViewOptions, loader::ConfigFile, settings::Settings, ProjectKey, ProjectNode};
+
+pub const SESSION_KEY: &str = "session";
+pub const SESSION_EXTENSION: &str = ".session.json";
+
+/// A snapshot of an analysis which can be restored later or shared with others.
+///
+/// The token is never part of a session.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Session {
+    pub settings: Settings,
+    pub data: BTreeMap<ProjectKey, Project>,
+    pub project_configs: BTreeMap<ProjectKey, Vec<ConfigFile>>,
+    /// Node locations by project.
+    pub node_positions: BTreeMap<ProjectKey, [f32; 2]>,
+    pub view: ViewOptions,
+}
+
+impl Session {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// The path of the named session in `dir`.
+///
+/// Names must not contain path separators or `..`, so sessions always stay in `dir`.
+pub fn session_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+        return Err(format!("invalid session name {name:?}"));
+    }
+    Ok(dir.join(format!("{name}{SESSION_EXTENSION}")))
+}
+
+/// Names of all sessions stored in `dir`, sorted.
+pub fn session_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(SESSION_EXTENSION))
+                .map(str::to_owned)
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+impl super::ConfigAnalyzer {
+    pub(crate) fn to_session(&self) -> Session {
+        let node_positions = self
+            .graph
+            .iter()
+            .flat_map(|graph| graph.nodes_iter())
+            .filter_map(|(_, node)| {
+                let location = node.location();
+                node.data()
+                    .and_then(ProjectNode::project_key)
+                    .map(|key| (key.clone(), [location.x, location.y]))
+            })
+            .collect();
+        Session {
+            settings: self.settings.clone(),
+            data: self.data.clone(),
+            project_configs: self.project_configs.clone(),
+            node_positions,
+            view: self.view.clone(),
+        }
+    }
+
+    /// Names of the stored sessions, read from the sessions directory once until refreshed.
+    pub(crate) fn session_names(&mut self) -> Vec<String> {
+        let dir = &self.settings.sessions_dir;
+        self.session_names
+            .get_or_insert_with(|| session_names(dir))
+            .clone()
+    }
+
+    pub(crate) fn restore_session(&mut self, session: Session) {
+        self.settings = session.settings;
+        self.session_names = None;
+        self.data = session.data;
+        self.project_configs = session.project_configs;
+        self.view = session.view;
+        self.graph = None;
+        if !self.project_configs.is_empty() {
+            self.update_project_dependencies();
+            self.generate_graph();
+        }
+        if let Some(graph) = self.graph.as_mut() {
+            let indices: Vec<_> = graph.nodes_iter().map(|(index, _)| index).collect();
+            for index in indices {
+                let node = graph.node_mut(index).unwrap();
+                let position = node
+                    .data()
+                    .and_then(ProjectNode::project_key)
+                    .and_then(|key| session.node_positions.get(key));
+                if let Some([x, y]) = position.copied() {
+                    node.set_location(Vec2::new(x, y));
+                }
+            }
+        }
+    }
+
+    pub(crate) fn sessions_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Sessions").show(ui, |ui| {
+            let dir = self.settings.sessions_dir.clone();
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.session_name);
+                if ui.button("Save").clicked() {
+                    match session_path(&dir, &self.session_name) {
+                        Ok(path) => match self.to_session().save(&path) {
+                            Ok(()) => log::info!("Saved session to {}", path.display()),
+                            Err(e) => {
+                                log::error!("Failed to save session {}: {e}", path.display())
+                            }
+                        },
+                        Err(e) => log::error!("{e}"),
+                    }
+                    self.session_names = None;
+                }
+                if ui.button("Refresh").clicked() {
+                    self.session_names = None;
+                }
+            });
+            for name in self.session_names() {
+                if ui.button(format!("Open {name}")).clicked() {
+                    let Ok(path) = session_path(&dir, &name) else {
+                        continue;
+                    };
+                    match Session::open(&path) {
+                        Ok(session) => {
+                            self.restore_session(session);
+                            self.session_name = name;
+                        }
+                        Err(e) => log::error!("Failed to open session {}: {e}", path.display()),
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{session_path, Session};
+    use crate::ProjectKey;
+    use std::path::Path;
+
+    #[test]
+    pub fn session_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("config-analyzer-{}", std::process::id()));
+        let path = session_path(&dir, "review").unwrap();
+        let mut session = Session::default();
+        session.settings.instances[0].add_group("platform");
+        session
+            .node_positions
+            .insert(ProjectKey::new("gitlab.com", 42), [1.0, 2.0]);
+        session.save(&path).unwrap();
+
+        let restored = Session::open(&path).unwrap();
+        assert_eq!(restored.settings, session.settings);
+        assert_eq!(restored.node_positions, session.node_positions);
+        assert_eq!(super::session_names(&dir), vec!["review".to_owned()]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        for name in ["../review", "a/b", "a\\b", "..", " "] {
+            assert!(session_path(Path::new("sessions"), name).is_err(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use std::path::PathBuf;
+
+use eframe::egui;
+use gitlab::{
+    api::{users::CurrentUser, ApiError, Query},
+    RestError, UserPublic,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    credentials::{Instance, Token, TokenSource},
+    environment::{default_environments, EnvironmentPattern},
+    gitlab_group::{available_groups, ProjectDiscovery},
+    gitlab_version::{Version, VersionEndpoint},
+};
+
+/// Connection settings which are persisted between runs.
+///
+/// The token is not part of the settings and is never written to disk.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Settings {
+    pub instances: Vec<Instance>,
+    /// Index into `instances` of the instance to connect to.
+    pub instance: usize,
+    pub discovery: ProjectDiscovery,
+    /// Directory holding the named analysis sessions.
+    pub sessions_dir: PathBuf,
+    /// The policy configuration files are checked against.
+    pub policy_file: PathBuf,
+    /// Patterns recognizing the environment of tasks without an environment field.
+    pub environments: Vec<EnvironmentPattern>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            instances: vec![Instance::default()],
+            instance: 0,
+            discovery: ProjectDiscovery::default(),
+            sessions_dir: PathBuf::from("sessions"),
+            policy_file: PathBuf::from("policy.json"),
+            environments: default_environments(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn current_instance(&self) -> Option<&Instance> {
+        self.instances.get(self.instance)
+    }
+}
+
+fn validate_connection(client: &gitlab::Gitlab) -> Result<String, ApiError<RestError>> {
+    let user: UserPublic = CurrentUser::builder().build().unwrap().query(client)?;
+    let version: Version = VersionEndpoint.query(client)?;
+    Ok(format!(
+        "Connected as {} to GitLab {}",
+        user.username, version.version
+    ))
+}
+
+/// A client of a mock GitLab server, answering the connection check.
+#[cfg(test)]
+pub fn test_client(server: &mut mockito::ServerGuard) -> gitlab::Gitlab {
+    server
+        .mock("GET", "/api/v4/user")
+        .match_query(mockito::Matcher::Any)
+        .with_body(
+            r#"{"username": "analyzer", "name": "Analyzer", "id": 1, "state": "active",
+                "web_url": "", "skype": "", "linkedin": "", "twitter": "", "website_url": "",
+                "email": "", "color_scheme_id": 1, "projects_limit": 0, "identities": [],
+                "can_create_group": false, "can_create_project": false,
+                "two_factor_enabled": false, "external": false}"#,
+        )
+        .create();
+    gitlab::Gitlab::new_insecure(server.host_with_port(), "token").unwrap()
+}
+
+impl super::ConfigAnalyzer {
+    /// Connects to every enabled instance.
+    pub(crate) fn connect(&mut self) {
+        self.clients.clear();
+        self.connection_status.clear();
+        for instance in self.settings.instances.iter().filter(|i| i.enabled) {
+            let prompt = self
+                .token_inputs
+                .get(&instance.name)
+                .map(String::as_str)
+                .unwrap_or_default();
+            let token = match instance.token(prompt) {
+                Ok(token) => token,
+                Err(e) => {
+                    log::error!("Failed to get token for {}: {e}", instance.name);
+                    self.connection_status
+                        .insert(instance.name.clone(), Err(e.to_string()));
+                    continue;
+                }
+            };
+            match gitlab::Gitlab::new(instance.host(), token.expose()) {
+                Ok(client) => {
+                    let status = validate_connection(&client).map_err(|e| e.to_string());
+                    match &status {
+                        Ok(message) => {
+                            log::info!("{}: {message}", instance.name);
+                            self.clients.insert(instance.host(), client);
+                        }
+                        // without a valid connection the instance counts as not connected
+                        Err(e) => log::error!("Failed to validate connection to Gitlab {e}"),
+                    }
+                    self.connection_status.insert(instance.name.clone(), status);
+                }
+                Err(e) => {
+                    log::error!("Failed to connect to Gitlab {e}");
+                    self.connection_status
+                        .insert(instance.name.clone(), Err(e.to_string()));
+                }
+            }
+        }
+    }
+
+    fn instance_ui(&mut self, ui: &mut egui::Ui) {
+        let selected = self
+            .settings
+            .current_instance()
+            .map(|instance| instance.name.clone())
+            .unwrap_or_default();
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Instance")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (index, instance) in self.settings.instances.iter().enumerate() {
+                        ui.selectable_value(&mut self.settings.instance, index, &instance.name);
+                    }
+                });
+            if ui.small_button("+").clicked() {
+                self.settings.instances.push(Instance {
+                    name: format!("instance-{}", self.settings.instances.len() + 1),
+                    ..Default::default()
+                });
+                self.settings.instance = self.settings.instances.len() - 1;
+            }
+            if self.settings.instances.len() > 1 && ui.small_button("-").clicked() {
+                self.settings.instances.remove(self.settings.instance);
+                self.settings.instance = 0;
+            }
+        });
+
+        let Some(instance) = self.settings.instances.get_mut(self.settings.instance) else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            let name_label = ui.label("Name: ");
+            ui.text_edit_singleline(&mut instance.name)
+                .labelled_by(name_label.id);
+        });
+        ui.horizontal(|ui| {
+            let url_label = ui.label("Hostname: ");
+            ui.text_edit_singleline(&mut instance.url)
+                .labelled_by(url_label.id);
+        });
+        ui.checkbox(&mut instance.enabled, "Include in analysis");
+        let sources = [
+            TokenSource::default(),
+            TokenSource::File {
+                path: Default::default(),
+            },
+            TokenSource::Keyring,
+            TokenSource::Glab { config: None },
+            TokenSource::Prompt,
+        ];
+        egui::ComboBox::from_label("Token source")
+            .selected_text(instance.token_source.label())
+            .show_ui(ui, |ui| {
+                for source in sources {
+                    let label = source.label();
+                    let current = instance.token_source.label() == label;
+                    if ui.selectable_label(current, label).clicked() && !current {
+                        instance.token_source = source;
+                    }
+                }
+            });
+        match &mut instance.token_source {
+            TokenSource::Environment { variable } => {
+                ui.horizontal(|ui| {
+                    ui.label("Variable: ");
+                    ui.text_edit_singleline(variable);
+                });
+            }
+            TokenSource::File { path } => {
+                let mut text = path.display().to_string();
+                ui.horizontal(|ui| {
+                    ui.label("Path: ");
+                    if ui.text_edit_singleline(&mut text).changed() {
+                        *path = PathBuf::from(&text);
+                    }
+                });
+            }
+            TokenSource::Glab { config } => {
+                let mut text = config
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default();
+                ui.horizontal(|ui| {
+                    ui.label("Config: ");
+                    if ui.text_edit_singleline(&mut text).changed() {
+                        *config = (!text.is_empty()).then(|| PathBuf::from(&text));
+                    }
+                });
+            }
+            TokenSource::Keyring | TokenSource::Prompt => {
+                let token_input = self.token_inputs.entry(instance.name.clone()).or_default();
+                ui.horizontal(|ui| {
+                    let token_label = ui.label("Token: ");
+                    ui.add(egui::TextEdit::singleline(token_input).password(true))
+                        .labelled_by(token_label.id);
+                });
+                if instance.token_source == TokenSource::Keyring
+                    && ui.button("Store in keyring").clicked()
+                {
+                    match instance.store_in_keyring(&Token::new(token_input.as_str())) {
+                        Ok(()) => {
+                            log::info!("Stored token of {} in keyring", instance.name);
+                            token_input.clear();
+                        }
+                        Err(e) => log::error!("Failed to store token in keyring: {e}"),
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Settings")
+            .default_open(self.clients.is_empty())
+            .show(ui, |ui| {
+                self.instance_ui(ui);
+                if ui.button("Connect to Gitlab").clicked() {
+                    self.connect();
+                }
+                for (name, status) in &self.connection_status {
+                    match status {
+                        Ok(message) => {
+                            ui.label(format!("{name}: {message}"));
+                        }
+                        Err(e) => {
+                            ui.colored_label(ui.visuals().error_fg_color, format!("{name}: {e}"));
+                        }
+                    }
+                }
+
+                let Some(instance) = self.settings.instances.get_mut(self.settings.instance) else {
+                    return;
+                };
+                ui.separator();
+                ui.label(format!("Groups on {}", instance.name));
+                let mut removed = None;
+                for (index, group) in instance.groups.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("x").clicked() {
+                            removed = Some(index);
+                        }
+                        ui.label(group);
+                    });
+                }
+                if let Some(index) = removed {
+                    instance.groups.remove(index);
+                }
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.new_group);
+                    if ui.button("Add").clicked() && instance.add_group(&self.new_group) {
+                        self.new_group.clear();
+                    }
+                });
+
+                if let Some(client) = self.clients.get(&instance.host()) {
+                    if ui.button("Fetch groups").clicked() {
+                        match available_groups(client) {
+                            Ok(groups) => self.available_groups = groups,
+                            Err(e) => log::error!("Failed to fetch groups {e}"),
+                        }
+                    }
+                }
+                if !self.available_groups.is_empty() {
+                    egui::ComboBox::from_id_source("group_picker")
+                        .selected_text("Pick a group")
+                        .show_ui(ui, |ui| {
+                            for group in &self.available_groups {
+                                if ui.selectable_label(false, &group.full_path).clicked() {
+                                    instance.add_group(&group.full_path);
+                                }
+                            }
+                        });
+                }
+            });
+    }
+}
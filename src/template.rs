@@ -0,0 +1,232 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::{Map, Value};
+
+use crate::configuration_schema::{Task, VariableShareConfig};
+
+/// The parameter holding the name of the environment unless declared otherwise.
+pub const ENVIRONMENT_PARAMETER: &str = "ENV";
+
+/// Parameters declared under this name apply to all environments.
+pub const ALL_ENVIRONMENTS: &str = "*";
+
+/// A configuration with the parameters of an environment filled in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expansion {
+    pub environment: Option<String>,
+    pub config: VariableShareConfig,
+    /// Placeholders without a value, one message each.
+    pub errors: Vec<String>,
+    /// The tasks before expansion, in the order of the expanded tasks. Empty for
+    /// configurations without parameters.
+    pub template: Vec<Task>,
+}
+
+/// Replaces the `${NAME}` placeholders of `text`, collecting names without a value.
+pub fn expand(
+    text: &str,
+    parameters: &BTreeMap<String, String>,
+    unresolved: &mut BTreeSet<String>,
+) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = &after[..end];
+        match parameters.get(name) {
+            Some(value) => expanded.push_str(value),
+            None => {
+                unresolved.insert(name.to_owned());
+                expanded.push_str(&rest[start..start + 3 + end]);
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Expands the placeholders of all strings and keys of `value`.
+///
+/// Keys expanding to the name of another key of the same object are collected in
+/// `collisions`, only the first of them is kept.
+fn expand_value(
+    value: &Value,
+    parameters: &BTreeMap<String, String>,
+    unresolved: &mut BTreeSet<String>,
+    collisions: &mut BTreeSet<String>,
+) -> Value {
+    match value {
+        Value::String(s) => Value::String(expand(s, parameters, unresolved)),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| expand_value(item, parameters, unresolved, collisions))
+                .collect(),
+        ),
+        // keys are expanded too, mapping keys are variable names
+        Value::Object(fields) => {
+            let mut expanded = Map::new();
+            for (key, item) in fields {
+                let key = expand(key, parameters, unresolved);
+                let item = expand_value(item, parameters, unresolved, collisions);
+                if expanded.contains_key(&key) {
+                    collisions.insert(key);
+                } else {
+                    expanded.insert(key, item);
+                }
+            }
+            Value::Object(expanded)
+        }
+        _ => value.clone(),
+    }
+}
+
+/// The tasks of `config` expanded for every environment it declares parameters for.
+///
+/// Without parameters for a specific environment the tasks are expanded once with the
+/// parameters for all environments. Configurations without parameters are not templates,
+/// their `${...}` strings are kept as they are.
+pub fn expand_config(config: &VariableShareConfig) -> Vec<Expansion> {
+    if config.parameters.is_empty() {
+        return vec![Expansion {
+            environment: None,
+            config: config.clone(),
+            errors: Vec::new(),
+            template: Vec::new(),
+        }];
+    }
+    let defaults = config
+        .parameters
+        .get(ALL_ENVIRONMENTS)
+        .cloned()
+        .unwrap_or_default();
+    let environments: Vec<Option<&String>> = config
+        .parameters
+        .keys()
+        .filter(|environment| *environment != ALL_ENVIRONMENTS)
+        .map(Some)
+        .collect();
+    let environments = if environments.is_empty() {
+        vec![None]
+    } else {
+        environments
+    };
+
+    let tasks = serde_json::to_value(&config.tasks).unwrap_or_default();
+    environments
+        .into_iter()
+        .map(|environment| {
+            let mut parameters = defaults.clone();
+            if let Some(environment) = environment {
+                parameters.extend(config.parameters[environment].clone());
+                parameters
+                    .entry(ENVIRONMENT_PARAMETER.to_owned())
+                    .or_insert_with(|| environment.clone());
+            }
+            let (mut unresolved, mut collisions) = (BTreeSet::new(), BTreeSet::new());
+            let expanded = expand_value(&tasks, &parameters, &mut unresolved, &mut collisions);
+            let in_environment = environment
+                .map(|environment| format!(" in environment {environment}"))
+                .unwrap_or_default();
+            let mut errors: Vec<String> = unresolved
+                .into_iter()
+                .map(|name| format!("Unresolved placeholder ${{{name}}}{in_environment}"))
+                .chain(collisions.into_iter().map(|key| {
+                    format!("Several keys expand to {key}{in_environment}, only the first is kept")
+                }))
+                .collect();
+            let mut expanded_config = config.clone();
+            match serde_json::from_value(expanded) {
+                Ok(tasks) => expanded_config.tasks = tasks,
+                Err(e) => errors.push(format!("Expanded tasks are not valid: {e}")),
+            }
+            Expansion {
+                environment: environment.cloned(),
+                config: expanded_config,
+                errors,
+                template: config.tasks.clone(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand_config, Expansion};
+    use crate::configuration_schema::{MappingTarget, SourceConfig, VariableShareConfig};
+
+    #[test]
+    pub fn placeholders_are_expanded_per_environment() {
+        let config: VariableShareConfig = serde_json::from_str(
+            r#"{"version": "1.0.0",
+                "parameters": {"*": {"REGION": "weu"}, "dev": {}, "prod": {"TIER": "premium"}},
+                "tasks": [{
+                    "source": {"type": "AzureKeyvault", "url": "https://app-${ENV}-${REGION}.vault.azure.net", "secretType": "secret"},
+                    "target": {"type": "Redis", "hostname": "cache-${ENV}"},
+                    "mapping": {"db-${ENV}": ["app:${TIER}:db"]}}]}"#,
+        )
+        .unwrap();
+
+        let expansions = expand_config(&config);
+        assert_eq!(expansions.len(), 2);
+        let (dev, prod) = (&expansions[0], &expansions[1]);
+        assert_eq!(dev.environment.as_deref(), Some("dev"));
+        assert_eq!(
+            dev.errors,
+            vec!["Unresolved placeholder ${TIER} in environment dev"]
+        );
+        assert!(prod.errors.is_empty());
+        let task = &prod.config.tasks[0];
+        let SourceConfig::AzureKeyvault(source) = &task.source else {
+            panic!("source changed its type");
+        };
+        assert_eq!(source.keyvault_url, "https://app-prod-weu.vault.azure.net");
+        assert_eq!(
+            task.mapping["db-prod"],
+            vec![MappingTarget::KeyOnly("app:premium:db".to_owned())]
+        );
+        // unresolved placeholders are kept
+        assert_eq!(
+            dev.config.tasks[0].mapping["db-dev"][0].key(),
+            "app:${TIER}:db"
+        );
+    }
+
+    #[test]
+    pub fn configs_without_parameters_and_colliding_keys() {
+        let config = |parameters: &str| -> VariableShareConfig {
+            serde_json::from_str(&format!(
+                r#"{{"version": "1.0.0", "parameters": {parameters},
+                    "tasks": [{{
+                        "source": {{"type": "HardCoded", "variables": {{"ENV": "${{CI_ENVIRONMENT_NAME}}"}}}},
+                        "target": {{"type": "ProcessEnvironment"}},
+                        "mapping": {{"db-${{ENV}}": ["DB"], "db-prod": ["DB_PROD"]}}}}]}}"#
+            ))
+            .unwrap()
+        };
+
+        // without parameters the file is not a template
+        let plain = config("{}");
+        assert_eq!(
+            expand_config(&plain),
+            vec![Expansion {
+                environment: None,
+                config: plain.clone(),
+                errors: Vec::new(),
+                template: Vec::new(),
+            }]
+        );
+
+        let expansions = expand_config(&config(r#"{"prod": {"CI_ENVIRONMENT_NAME": "prod"}}"#));
+        assert_eq!(
+            expansions[0].errors,
+            vec!["Several keys expand to db-prod in environment prod, only the first is kept"]
+        );
+    }
+}
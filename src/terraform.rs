@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+
+use gitlab::api::{raw, Query};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    configuration_schema::{GitlabProjectConfig, TerraformInputFileFormat},
+    gitlab_terraform_state::TerraformStateEndpoint,
+    ProjectKey,
+};
+
+/// The state a task reads when it names no environment.
+pub const DEFAULT_STATE_NAME: &str = "default";
+
+/// An output of a Terraform root module, without its value.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TerraformOutput {
+    /// The type constraint, e.g. `string` or `["list","string"]`.
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub sensitive: bool,
+}
+
+/// Outputs by name.
+pub type TerraformOutputs = BTreeMap<String, TerraformOutput>;
+
+/// An output as written by `terraform output -json` and in the `outputs` of a state.
+#[derive(Deserialize)]
+struct RawOutput {
+    #[serde(rename = "type", default)]
+    type_: Option<Value>,
+    #[serde(default)]
+    sensitive: bool,
+}
+
+#[derive(Deserialize)]
+struct RawState {
+    version: u64,
+    #[serde(default)]
+    outputs: BTreeMap<String, RawOutput>,
+}
+
+/// Extracts the outputs of a `terraform output -json` or state v4 document.
+pub fn parse_outputs(
+    content: &str,
+    format: &TerraformInputFileFormat,
+) -> Result<TerraformOutputs, String> {
+    let outputs = match format {
+        TerraformInputFileFormat::OutputJson => {
+            serde_json::from_str::<BTreeMap<String, RawOutput>>(content)
+                .map_err(|e| format!("Invalid terraform output: {e}"))?
+        }
+        TerraformInputFileFormat::State => {
+            let state: RawState = serde_json::from_str(content)
+                .map_err(|e| format!("Invalid terraform state: {e}"))?;
+            if state.version != 4 {
+                return Err(format!(
+                    "Unsupported terraform state version {}",
+                    state.version
+                ));
+            }
+            state.outputs
+        }
+    };
+    Ok(outputs
+        .into_iter()
+        .map(|(name, output)| {
+            let type_ = match output.type_ {
+                Some(Value::String(type_)) => type_,
+                Some(type_) => type_.to_string(),
+                None => "unknown".to_owned(),
+            };
+            let output = TerraformOutput {
+                type_,
+                sensitive: output.sensitive,
+            };
+            (name, output)
+        })
+        .collect())
+}
+
+/// The name of the GitLab-managed state a task reads.
+pub fn state_name(config: &GitlabProjectConfig) -> &str {
+    config.environment.as_deref().unwrap_or(DEFAULT_STATE_NAME)
+}
+
+/// Identifies the state `name` of `project` among the states read by a configuration file.
+pub fn state_key(project: &ProjectKey, name: &str) -> String {
+    format!("{project} {name}")
+}
+
+/// Downloads the GitLab-managed state `name` of `project` and extracts its outputs.
+pub fn fetch_state_outputs(
+    client: &gitlab::Gitlab,
+    project: u64,
+    name: &str,
+) -> Result<TerraformOutputs, String> {
+    let endpoint = TerraformStateEndpoint {
+        project,
+        name: name.to_owned(),
+    };
+    let content = raw(endpoint)
+        .query(client)
+        .map_err(|e| format!("Failed to get terraform state {name} of project {project}: {e}"))?;
+    parse_outputs(
+        &String::from_utf8_lossy(&content),
+        &TerraformInputFileFormat::State,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::Matcher;
+
+    use super::{fetch_state_outputs, parse_outputs, TerraformOutput};
+    use crate::{configuration_schema::TerraformInputFileFormat, settings::test_client};
+
+    #[test]
+    pub fn outputs_of_output_json_and_state() {
+        let output_json = r#"{
+            "db_password": {"sensitive": true, "type": "string", "value": "secret"},
+            "subnets": {"sensitive": false, "type": ["list", "string"], "value": ["a"]}
+        }"#;
+        let outputs = parse_outputs(output_json, &TerraformInputFileFormat::OutputJson).unwrap();
+        assert_eq!(
+            outputs["db_password"],
+            TerraformOutput {
+                type_: "string".to_owned(),
+                sensitive: true
+            }
+        );
+        assert_eq!(outputs["subnets"].type_, r#"["list","string"]"#);
+
+        let state = r#"{
+            "version": 4, "terraform_version": "1.5.0", "serial": 3, "lineage": "x",
+            "outputs": {"db_password": {"value": "secret", "type": "string", "sensitive": true}},
+            "resources": []
+        }"#;
+        let outputs = parse_outputs(state, &TerraformInputFileFormat::State).unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert!(outputs["db_password"].sensitive);
+
+        let old_state = r#"{"version": 3, "modules": []}"#;
+        assert!(parse_outputs(old_state, &TerraformInputFileFormat::State).is_err());
+    }
+
+    #[test]
+    pub fn outputs_of_gitlab_managed_state() {
+        let mut server = mockito::Server::new();
+        let client = test_client(&mut server);
+        let state = server
+            .mock("GET", "/api/v4/projects/42/terraform/state/production%2Feu")
+            .match_query(Matcher::Any)
+            .with_body(
+                r#"{"version": 4, "outputs": {"db_host": {"value": "db", "type": "string"}}}"#,
+            )
+            .create();
+        server
+            .mock("GET", "/api/v4/projects/42/terraform/state/missing")
+            .match_query(Matcher::Any)
+            .with_status(404)
+            .with_body(r#"{"message": "404 Not Found"}"#)
+            .create();
+
+        let outputs = fetch_state_outputs(&client, 42, "production/eu").unwrap();
+        assert!(!outputs["db_host"].sensitive);
+        state.assert();
+        assert!(fetch_state_outputs(&client, 42, "missing").is_err());
+    }
+}